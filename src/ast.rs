@@ -1,4 +1,5 @@
-use crate::token::Token;
+use crate::token::{Span, Token};
+use num_bigint::BigInt;
 use std::fmt::Display;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,10 +26,16 @@ pub enum Infix {
     Minus,
     Asterisk,
     Slash,
+    Percent,
+    Power,
     Lt,
     Gt,
+    Le,
+    Ge,
     Eq,
     Ne,
+    And,
+    Or,
 }
 
 impl Display for Infix {
@@ -38,32 +45,68 @@ impl Display for Infix {
             Infix::Minus => write!(f, "-"),
             Infix::Asterisk => write!(f, "*"),
             Infix::Slash => write!(f, "/"),
+            Infix::Percent => write!(f, "%"),
+            Infix::Power => write!(f, "**"),
             Infix::Lt => write!(f, "<"),
             Infix::Gt => write!(f, ">"),
+            Infix::Le => write!(f, "<="),
+            Infix::Ge => write!(f, ">="),
             Infix::Eq => write!(f, "=="),
             Infix::Ne => write!(f, "!="),
+            Infix::And => write!(f, "&&"),
+            Infix::Or => write!(f, "||"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Expr {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
     Ident(Ident),
     Int(i64),
+    BigInt(BigInt),
+    Float(f64),
     Bool(bool),
     String(String),
+    Template(Vec<TemplatePart>),
     Prefix(Prefix, Box<Expr>),
     Infix(Infix, Box<Expr>, Box<Expr>),
     If(Box<Expr>, BlockStmt, Option<BlockStmt>),
     Function(Vec<Ident>, BlockStmt),
     Call(Box<Expr>, Vec<Expr>),
+    Array(Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    Hash(Vec<(Expr, Expr)>),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Stmt {
-    Let(Ident, Expr),
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Literal(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Let(Ident, Expr, Option<String>),
+    Const(Ident, Expr, Option<String>),
+    Assign(Ident, Option<Infix>, Expr),
     Return(Expr),
     Expr(Expr),
+    While(Expr, BlockStmt),
+    For(Ident, Expr, BlockStmt),
+    Break,
+    Continue,
 }
 
 pub type BlockStmt = Vec<Stmt>;
@@ -73,22 +116,30 @@ pub type Program = Vec<Stmt>;
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Precedence {
     Lowest,
+    Or,
+    And,
     Equals,
     LessGreater,
     Sum,
     Product,
     Prefix,
+    Power,
     Call,
+    Index,
 }
 
 impl From<&Token> for Precedence {
     fn from(token: &Token) -> Self {
         match token {
+            Token::OR => Precedence::Or,
+            Token::AND => Precedence::And,
             Token::EQ | Token::NE => Precedence::Equals,
-            Token::LT | Token::GT => Precedence::LessGreater,
+            Token::LT | Token::GT | Token::LE | Token::GE => Precedence::LessGreater,
             Token::PLUS | Token::MINUS => Precedence::Sum,
-            Token::ASTERISK | Token::SLASH => Precedence::Product,
+            Token::ASTERISK | Token::SLASH | Token::PERCENT => Precedence::Product,
+            Token::POWER => Precedence::Power,
             Token::LPAREN => Precedence::Call,
+            Token::LBRACKET => Precedence::Index,
             _ => Precedence::Lowest,
         }
     }
@@ -1,45 +1,304 @@
 use colored::Colorize;
 use rust_monkey::{
-    environment::Environment, evaluator::Evaluator, lexer::Lexer, object::Object, parser::Parser,
+    ast::Program,
+    builtins::get_builtin,
+    compiler::Compiler,
+    diagnostics::Diagnostic,
+    environment::Environment,
+    error::RuntimeError,
+    evaluator::Evaluator,
+    lexer::Lexer,
+    object::Object,
+    parser::Parser,
+    symbol_table::{SymbolScope, SymbolTable},
+    token::Token,
+    vm::Vm,
 };
-use rustyline::DefaultEditor;
+use rustyline::{error::ReadlineError, DefaultEditor};
 use std::{cell::RefCell, rc::Rc};
 
+enum Engine {
+    Eval(Evaluator),
+    Vm {
+        symbol_table: SymbolTable,
+        constants: Vec<Object>,
+        globals: Vec<Option<Object>>,
+    },
+}
+
+impl Engine {
+    fn new(name: &str, args: &[String]) -> Option<Self> {
+        let args = Object::Array(args.iter().cloned().map(Object::String).collect());
+        match name {
+            "eval" => {
+                let mut env = Environment::new();
+                env.set("args".to_string(), &args);
+                Some(Engine::Eval(Evaluator::new(Rc::new(RefCell::new(env)))))
+            }
+            "vm" => {
+                let mut symbol_table = SymbolTable::new();
+                let symbol = symbol_table.define("args");
+                let mut globals = vec![None; symbol.index + 1];
+                globals[symbol.index] = Some(args);
+                Some(Engine::Vm {
+                    symbol_table,
+                    constants: Vec::new(),
+                    globals,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn diagnose(&self, err: &RuntimeError) -> Diagnostic {
+        let mut diagnostic = err.to_diagnostic();
+        if let Engine::Eval(evaluator) = self {
+            for frames in evaluator.stack_trace().chunk_by(|a, b| a == b) {
+                diagnostic = diagnostic.with_note(&frames[0].to_string());
+                if frames.len() > 1 {
+                    diagnostic = diagnostic.with_note(&format!(
+                        "previous frame repeated {} more times",
+                        frames.len() - 1
+                    ));
+                }
+            }
+        }
+        diagnostic
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<Object> {
+        match self {
+            Engine::Eval(evaluator) => evaluator.lookup(name),
+            Engine::Vm {
+                symbol_table,
+                globals,
+                ..
+            } => {
+                let symbol = symbol_table.resolve(name)?;
+                match symbol.scope {
+                    SymbolScope::Global => globals.get(symbol.index).cloned().flatten(),
+                    SymbolScope::Builtin => get_builtin(name),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn run(&mut self, program: Program) -> Result<Object, RuntimeError> {
+        match self {
+            Engine::Eval(evaluator) => evaluator.eval(program),
+            Engine::Vm {
+                symbol_table,
+                constants,
+                globals,
+            } => {
+                let mut compiler = Compiler::new_with_state(
+                    std::mem::take(symbol_table),
+                    std::mem::take(constants),
+                );
+                let compiled = compiler.compile(&program);
+                let bytecode = compiler.bytecode();
+                (*symbol_table, *constants) = compiler.into_state();
+                compiled?;
+                let mut vm = Vm::new_with_globals(bytecode, std::mem::take(globals));
+                let result = vm.run();
+                let object = vm.last_popped().unwrap_or(Object::Null);
+                *globals = vm.into_globals();
+                result.map(|_| object)
+            }
+        }
+    }
+}
+
+fn is_incomplete(input: &str) -> bool {
+    let mut lexer = Lexer::new(input);
+    let mut depth = 0;
+    let mut last = Token::EOF;
+    loop {
+        let (token, _) = lexer.next_token();
+        match token {
+            Token::EOF => break,
+            Token::LPAREN | Token::LBRACE | Token::LBRACKET | Token::INTERPOLATION => depth += 1,
+            Token::RPAREN | Token::RBRACE | Token::RBRACKET => depth -= 1,
+            _ => {}
+        }
+        last = token;
+    }
+    let unterminated = lexer
+        .errors()
+        .iter()
+        .any(|err| err.code.as_deref() == Some("E0005"));
+    unterminated
+        || depth > 0
+        || matches!(
+            last,
+            Token::ASSIGN
+                | Token::PLUSASSIGN
+                | Token::MINUSASSIGN
+                | Token::ASTERISKASSIGN
+                | Token::SLASHASSIGN
+                | Token::PLUS
+                | Token::MINUS
+                | Token::BANG
+                | Token::ASTERISK
+                | Token::SLASH
+                | Token::PERCENT
+                | Token::POWER
+                | Token::LT
+                | Token::GT
+                | Token::LE
+                | Token::GE
+                | Token::EQ
+                | Token::NE
+                | Token::AND
+                | Token::OR
+                | Token::COMMA
+                | Token::COLON
+                | Token::LET
+                | Token::CONST
+                | Token::RETURN
+                | Token::ELSE
+                | Token::FUNCTION
+                | Token::IF
+                | Token::WHILE
+                | Token::FOR
+                | Token::IN
+                | Token::DOC(_)
+        )
+}
+
+fn parse_and_run(engine: &mut Engine, source: &str, print_result: bool) -> i32 {
+    let lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    if !parser.errors().is_empty() {
+        for err in parser.errors() {
+            eprint!("{}", err.render(source));
+        }
+        return 1;
+    }
+    match engine.run(program) {
+        Ok(Object::Null) => 0,
+        Ok(object) => {
+            if print_result {
+                println!("{}", object);
+            }
+            0
+        }
+        Err(err) => {
+            eprint!("{}", engine.diagnose(&err).render(source));
+            1
+        }
+    }
+}
+
+fn run_command(engine: &mut Engine, line: &str) {
+    match line.split_whitespace().collect::<Vec<_>>()[..] {
+        [":doc", name] => match engine.lookup(name) {
+            Some(object) => match object.doc() {
+                Some(doc) => println!("{}", doc),
+                None => println!("no documentation for `{}`", name),
+            },
+            None => println!("{}", format!("identifier not found: {}", name).red()),
+        },
+        _ => println!("{}", "usage: :doc <name>".red()),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: main [--engine eval|vm] [-e <expr> | <file>] [args...]");
+    std::process::exit(2);
+}
+
 fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut engine_name = "eval".to_string();
+    let mut file = None;
+    let mut expr = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--engine" => engine_name = args.next().unwrap_or_else(|| usage()),
+            "-e" => {
+                expr = Some(args.next().unwrap_or_else(|| usage()));
+                break;
+            }
+            arg if arg.starts_with('-') => usage(),
+            _ => {
+                file = Some(arg);
+                break;
+            }
+        }
+    }
+    let script_args = args.collect::<Vec<_>>();
+    let mut engine = match Engine::new(&engine_name, &script_args) {
+        Some(engine) => engine,
+        None => {
+            eprintln!("--engine expects `eval` or `vm`");
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(expr) = expr {
+        std::process::exit(parse_and_run(&mut engine, &expr, true));
+    }
+    if let Some(file) = file {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("cannot read {}: {}", file, err);
+                std::process::exit(1);
+            }
+        };
+        std::process::exit(parse_and_run(&mut engine, &source, false));
+    }
+
     println!(
         "Hello {}! This is the Monkey programming language!",
         whoami::username()
     );
     println!("Feel free to type in commands");
 
-    let env = Rc::new(RefCell::new(Environment::new()));
-    let mut evaluator = Evaluator::new(env);
-
+    let mut source = String::new();
+    let mut position = 0;
     let mut rl = DefaultEditor::new().unwrap();
     loop {
-        match rl.readline(">> ") {
+        let prompt = if source.len() > position {
+            ".. "
+        } else {
+            ">> "
+        };
+        match rl.readline(prompt) {
+            Ok(line) if source.len() == position && line.trim_start().starts_with(':') => {
+                run_command(&mut engine, &line);
+            }
             Ok(line) => {
-                let lexer = Lexer::new(&line);
+                let continued = source.len() > position;
+                source.push_str(&line);
+                source.push('\n');
+                if is_incomplete(&source[position..]) && !(continued && line.trim().is_empty()) {
+                    continue;
+                }
+                let lexer = Lexer::new_at(&source, position);
                 let mut parser = Parser::new(lexer);
                 let program = parser.parse_program();
+                position = source.len();
                 if !parser.errors().is_empty() {
                     println!("{}", "Woops! We ran into some monkey business here!".red());
-                    println!(" {}", "parse error:".red());
                     for err in parser.errors() {
-                        println!("\t{}", err.red());
+                        print!("{}", err.render(&source).red());
                     }
                     continue;
                 }
-                if let Some(evaluated) = evaluator.eval(program) {
-                    match evaluated {
-                        Object::Error(err) => {
-                            println!(" {}", "evaluate error:".red());
-                            println!("\t{}", err.red())
-                        }
-                        object => println!("{}", object),
-                    }
+                match engine.run(program) {
+                    Ok(Object::Null) => {}
+                    Ok(object) => println!("{}", object),
+                    Err(err) => print!("{}", engine.diagnose(&err).render(&source).red()),
                 }
             }
+            Err(ReadlineError::Interrupted) if source.len() > position => {
+                source.push('\n');
+                position = source.len();
+            }
             Err(err) => {
                 println!("Error: {}", err);
                 break;
@@ -0,0 +1,105 @@
+use crate::{
+    error::RuntimeError,
+    object::{Builtin, BuiltinFunction, Object},
+    token::Span,
+};
+
+pub const BUILTINS: &[(&str, BuiltinFunction)] = &[
+    ("len", len),
+    ("puts", puts),
+    ("first", first),
+    ("last", last),
+    ("rest", rest),
+    ("push", push),
+    ("doc", doc),
+];
+
+pub fn get_builtin(name: &str) -> Option<Object> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|&(name, func)| Object::Builtin(Builtin { name, func }))
+}
+
+fn check_args_len(args: &[Object], expected: usize) -> Result<(), RuntimeError> {
+    if args.len() != expected {
+        return Err(RuntimeError::ArityMismatch {
+            expected,
+            given: args.len(),
+            span: Span::default(),
+            defined: None,
+        });
+    }
+    Ok(())
+}
+
+fn unsupported_arg(name: &str, object: &Object) -> RuntimeError {
+    RuntimeError::UnsupportedArgument {
+        name: name.to_string(),
+        arg: object.get_type(),
+        span: Span::default(),
+    }
+}
+
+fn len(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_args_len(&args, 1)?;
+    match &args[0] {
+        Object::String(value) => Ok(Object::Int(value.chars().count() as i64)),
+        Object::Array(elements) => Ok(Object::Int(elements.len() as i64)),
+        object => Err(unsupported_arg("len", object)),
+    }
+}
+
+fn puts(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    for arg in args {
+        println!("{}", arg);
+    }
+    Ok(Object::Null)
+}
+
+fn first(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_args_len(&args, 1)?;
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.first().cloned().unwrap_or(Object::Null)),
+        object => Err(unsupported_arg("first", object)),
+    }
+}
+
+fn last(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_args_len(&args, 1)?;
+    match &args[0] {
+        Object::Array(elements) => Ok(elements.last().cloned().unwrap_or(Object::Null)),
+        object => Err(unsupported_arg("last", object)),
+    }
+}
+
+fn rest(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_args_len(&args, 1)?;
+    match &args[0] {
+        Object::Array(elements) if elements.is_empty() => Ok(Object::Null),
+        Object::Array(elements) => Ok(Object::Array(elements[1..].to_vec())),
+        object => Err(unsupported_arg("rest", object)),
+    }
+}
+
+fn push(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_args_len(&args, 2)?;
+    match &args[0] {
+        Object::Array(elements) => {
+            let mut elements = elements.clone();
+            elements.push(args[1].clone());
+            Ok(Object::Array(elements))
+        }
+        object => Err(unsupported_arg("push", object)),
+    }
+}
+
+fn doc(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_args_len(&args, 1)?;
+    match &args[0] {
+        object @ (Object::Function(_) | Object::Closure(_) | Object::Builtin(_)) => Ok(object
+            .doc()
+            .map_or(Object::Null, |doc| Object::String(doc.to_string()))),
+        object => Err(unsupported_arg("doc", object)),
+    }
+}
@@ -0,0 +1,263 @@
+pub type Instructions = Vec<u8>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Opcode {
+    Constant,
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    True,
+    False,
+    Equal,
+    NotEqual,
+    GreaterThan,
+    LessThan,
+    Minus,
+    Bang,
+    JumpNotTruthy,
+    Jump,
+    Null,
+    GetGlobal,
+    SetGlobal,
+    Array,
+    Hash,
+    Index,
+    Call,
+    ReturnValue,
+    Return,
+    GetLocal,
+    SetLocal,
+    GetBuiltin,
+    Closure,
+    GetFree,
+    Iter,
+    ForIter,
+    JumpNotTruthyOrPop,
+    JumpTruthyOrPop,
+    Mod,
+    Pow,
+    LessEqual,
+    GreaterEqual,
+    Template,
+    CaptureLocal,
+    CaptureFree,
+    SetFree,
+    Drop,
+    Raise,
+    AssignGlobal,
+    GetAssignGlobal,
+}
+
+const OPCODES: &[Opcode] = &[
+    Opcode::Constant,
+    Opcode::Pop,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::True,
+    Opcode::False,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::GreaterThan,
+    Opcode::LessThan,
+    Opcode::Minus,
+    Opcode::Bang,
+    Opcode::JumpNotTruthy,
+    Opcode::Jump,
+    Opcode::Null,
+    Opcode::GetGlobal,
+    Opcode::SetGlobal,
+    Opcode::Array,
+    Opcode::Hash,
+    Opcode::Index,
+    Opcode::Call,
+    Opcode::ReturnValue,
+    Opcode::Return,
+    Opcode::GetLocal,
+    Opcode::SetLocal,
+    Opcode::GetBuiltin,
+    Opcode::Closure,
+    Opcode::GetFree,
+    Opcode::Iter,
+    Opcode::ForIter,
+    Opcode::JumpNotTruthyOrPop,
+    Opcode::JumpTruthyOrPop,
+    Opcode::Mod,
+    Opcode::Pow,
+    Opcode::LessEqual,
+    Opcode::GreaterEqual,
+    Opcode::Template,
+    Opcode::CaptureLocal,
+    Opcode::CaptureFree,
+    Opcode::SetFree,
+    Opcode::Drop,
+    Opcode::Raise,
+    Opcode::AssignGlobal,
+    Opcode::GetAssignGlobal,
+];
+
+impl Opcode {
+    pub fn operand_widths(&self) -> &'static [usize] {
+        match self {
+            Opcode::Constant
+            | Opcode::JumpNotTruthy
+            | Opcode::Jump
+            | Opcode::ForIter
+            | Opcode::JumpNotTruthyOrPop
+            | Opcode::JumpTruthyOrPop
+            | Opcode::GetGlobal
+            | Opcode::SetGlobal
+            | Opcode::Array
+            | Opcode::Hash
+            | Opcode::Template
+            | Opcode::Raise
+            | Opcode::AssignGlobal
+            | Opcode::GetAssignGlobal => &[2],
+            Opcode::Call
+            | Opcode::GetLocal
+            | Opcode::SetLocal
+            | Opcode::GetBuiltin
+            | Opcode::GetFree
+            | Opcode::CaptureLocal
+            | Opcode::CaptureFree
+            | Opcode::SetFree
+            | Opcode::Drop => &[1],
+            Opcode::Closure => &[2, 1],
+            _ => &[],
+        }
+    }
+
+    pub fn operand_names(&self) -> &'static [&'static str] {
+        match self {
+            Opcode::Constant => &["constants"],
+            Opcode::JumpNotTruthy
+            | Opcode::Jump
+            | Opcode::ForIter
+            | Opcode::JumpNotTruthyOrPop
+            | Opcode::JumpTruthyOrPop => &["instructions"],
+            Opcode::GetGlobal
+            | Opcode::SetGlobal
+            | Opcode::AssignGlobal
+            | Opcode::GetAssignGlobal => &["global variables"],
+            Opcode::Array => &["array elements"],
+            Opcode::Hash => &["hash pairs"],
+            Opcode::Template => &["template parts"],
+            Opcode::Call => &["call arguments"],
+            Opcode::GetLocal | Opcode::SetLocal | Opcode::CaptureLocal => &["local variables"],
+            Opcode::GetBuiltin => &["builtins"],
+            Opcode::GetFree | Opcode::CaptureFree | Opcode::SetFree => &["captured variables"],
+            Opcode::Closure => &["constants", "captured variables"],
+            Opcode::Drop => &["stack values"],
+            Opcode::Raise => &["errors"],
+            _ => &[],
+        }
+    }
+}
+
+pub fn operand_limit(width: usize) -> usize {
+    1 << (8 * width)
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        OPCODES
+            .get(byte as usize)
+            .copied()
+            .ok_or(format!("opcode {} undefined", byte))
+    }
+}
+
+pub fn make(op: Opcode, operands: &[usize]) -> Instructions {
+    let mut instruction = vec![op as u8];
+    for (operand, width) in operands.iter().zip(op.operand_widths()) {
+        match width {
+            2 => instruction.extend_from_slice(&(*operand as u16).to_be_bytes()),
+            1 => instruction.push(*operand as u8),
+            _ => unreachable!(),
+        }
+    }
+    instruction
+}
+
+pub fn read_u16(ins: &[u8], position: usize) -> usize {
+    u16::from_be_bytes([ins[position], ins[position + 1]]) as usize
+}
+
+pub fn read_u8(ins: &[u8], position: usize) -> usize {
+    ins[position] as usize
+}
+
+pub fn disassemble(ins: &[u8]) -> String {
+    let mut out = String::new();
+    let mut position = 0;
+    while position < ins.len() {
+        let op = match Opcode::try_from(ins[position]) {
+            Ok(op) => op,
+            Err(err) => {
+                out.push_str(&format!("ERROR: {}\n", err));
+                position += 1;
+                continue;
+            }
+        };
+        let mut operands = Vec::new();
+        let mut offset = position + 1;
+        for width in op.operand_widths() {
+            match width {
+                2 => operands.push(read_u16(ins, offset)),
+                1 => operands.push(read_u8(ins, offset)),
+                _ => unreachable!(),
+            }
+            offset += width;
+        }
+        out.push_str(&format!("{:04} {:?}", position, op));
+        for operand in operands {
+            out.push_str(&format!(" {}", operand));
+        }
+        out.push('\n');
+        position = offset;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make() {
+        assert_eq!(
+            make(Opcode::Constant, &[65534]),
+            vec![Opcode::Constant as u8, 255, 254]
+        );
+        assert_eq!(
+            make(Opcode::GetLocal, &[255]),
+            vec![Opcode::GetLocal as u8, 255]
+        );
+        assert_eq!(
+            make(Opcode::Closure, &[65534, 255]),
+            vec![Opcode::Closure as u8, 255, 254, 255]
+        );
+        assert_eq!(make(Opcode::Add, &[]), vec![Opcode::Add as u8]);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let ins = [
+            make(Opcode::Add, &[]),
+            make(Opcode::GetLocal, &[1]),
+            make(Opcode::Constant, &[2]),
+            make(Opcode::Closure, &[65535, 255]),
+        ]
+        .concat();
+        assert_eq!(
+            disassemble(&ins),
+            "0000 Add\n0001 GetLocal 1\n0003 Constant 2\n0006 Closure 65535 255\n"
+        );
+    }
+}
@@ -0,0 +1,762 @@
+use crate::{
+    ast::{BlockStmt, Expr, ExprKind, Ident, Infix, Prefix, Program, Stmt, StmtKind, TemplatePart},
+    code::{make, operand_limit, Instructions, Opcode},
+    error::RuntimeError,
+    object::{CompiledFunction, HashKey, Object},
+    symbol_table::{Symbol, SymbolScope, SymbolTable},
+    token::Span,
+    RED_ZONE, STACK_SEGMENT,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+#[derive(Debug, Clone)]
+pub struct Bytecode {
+    pub instructions: Instructions,
+    pub constants: Vec<Object>,
+    pub spans: Vec<(usize, Span)>,
+    pub errors: Vec<RuntimeError>,
+    pub global_symbols: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EmittedInstruction {
+    opcode: Opcode,
+    position: usize,
+}
+
+#[derive(Debug)]
+struct Loop {
+    start: usize,
+    pops: usize,
+    breaks: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+struct CompilationScope {
+    instructions: Instructions,
+    last_instruction: Option<EmittedInstruction>,
+    previous_instruction: Option<EmittedInstruction>,
+    loops: Vec<Loop>,
+    spans: Vec<(usize, Span)>,
+    errors: Vec<RuntimeError>,
+}
+
+#[derive(Debug)]
+pub struct Compiler {
+    constants: Vec<Object>,
+    constant_indices: HashMap<HashKey, usize>,
+    symbol_table: SymbolTable,
+    scopes: Vec<CompilationScope>,
+    span: Span,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler::new_with_state(SymbolTable::new(), Vec::new())
+    }
+
+    pub fn new_with_state(symbol_table: SymbolTable, constants: Vec<Object>) -> Self {
+        let constant_indices = constants
+            .iter()
+            .enumerate()
+            .filter_map(|(index, object)| Some((HashKey::try_from(object).ok()?, index)))
+            .collect();
+        Compiler {
+            constants,
+            constant_indices,
+            symbol_table,
+            scopes: vec![CompilationScope::default()],
+            span: Span::default(),
+        }
+    }
+
+    pub fn into_state(self) -> (SymbolTable, Vec<Object>) {
+        (self.symbol_table, self.constants)
+    }
+
+    pub fn bytecode(&self) -> Bytecode {
+        Bytecode {
+            instructions: self.current_instructions().clone(),
+            constants: self.constants.clone(),
+            spans: self.scopes.last().unwrap().spans.clone(),
+            errors: self.scopes.last().unwrap().errors.clone(),
+            global_symbols: self.symbol_table.global_symbols(),
+        }
+    }
+
+    pub fn compile(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        for stmt in program {
+            if let Err(err) = self.compile_stmt(stmt) {
+                while self.scopes.len() > 1 {
+                    self.leave_scope();
+                }
+                return Err(err);
+            }
+        }
+        if let Some(stmt) = program.last() {
+            if !matches!(stmt.kind, StmtKind::Expr(_) | StmtKind::Return(_)) {
+                self.emit(Opcode::Null, &[])?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_stmts(&mut self, stmts: &BlockStmt) -> Result<(), RuntimeError> {
+        for stmt in stmts {
+            self.compile_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn compile_stmt(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        let span = mem::replace(&mut self.span, stmt.span);
+        let compiled = self.compile_stmt_kind(stmt);
+        self.span = span;
+        compiled
+    }
+
+    fn compile_stmt_kind(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => {
+                self.compile_expr(expr)?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StmtKind::Return(expr) => {
+                self.compile_expr(expr)?;
+                self.emit(Opcode::ReturnValue, &[])?;
+            }
+            StmtKind::Let(Ident(name), expr, doc) => {
+                self.compile_binding(name, expr, doc, stmt.span, None)?
+            }
+            StmtKind::Const(Ident(name), expr, doc) => {
+                self.compile_binding(name, expr, doc, stmt.span, Some(stmt.span))?
+            }
+            StmtKind::Assign(Ident(name), infix, expr) => {
+                let symbol = match self.symbol_table.resolve(name) {
+                    Some(symbol) => symbol,
+                    None => self.symbol_table.define_forward(name),
+                };
+                let undeclared = RuntimeError::UndeclaredAssignment {
+                    name: name.clone(),
+                    span: stmt.span,
+                };
+                if infix.is_some() {
+                    match symbol.scope {
+                        SymbolScope::Global => {
+                            self.emit(Opcode::GetAssignGlobal, &[symbol.index])?;
+                        }
+                        SymbolScope::Builtin => return self.raise(undeclared),
+                        _ => self.load_symbol(&symbol)?,
+                    }
+                }
+                self.compile_expr(expr)?;
+                if let Some(infix) = infix {
+                    self.emit(infix_opcode(infix), &[])?;
+                }
+                match symbol {
+                    Symbol {
+                        scope: SymbolScope::Global,
+                        index,
+                        ..
+                    } => {
+                        self.emit(Opcode::AssignGlobal, &[index])?;
+                    }
+                    Symbol {
+                        scope: SymbolScope::Builtin,
+                        ..
+                    } => self.raise(undeclared)?,
+                    Symbol {
+                        constant: Some(declared),
+                        ..
+                    } => self.raise(RuntimeError::ConstantRebinding {
+                        name: name.clone(),
+                        span: stmt.span,
+                        declared,
+                    })?,
+                    symbol => self.store_symbol(&symbol)?,
+                }
+            }
+            StmtKind::While(cond, body) => {
+                let start = self.current_instructions().len();
+                self.compile_expr(cond)?;
+                let exit = self.emit(Opcode::JumpNotTruthy, &[9999])?;
+                self.compile_loop_body(start, exit, 0, body)?;
+            }
+            StmtKind::For(Ident(name), iterable, body) => {
+                self.compile_expr(iterable)?;
+                let span = mem::replace(&mut self.span, iterable.span);
+                self.emit(Opcode::Iter, &[])?;
+                self.span = span;
+                let start = self.current_instructions().len();
+                let exit = self.emit(Opcode::ForIter, &[9999])?;
+                match self.symbol_table.constant(name) {
+                    Some(declared) => self.raise(RuntimeError::ConstantRebinding {
+                        name: name.clone(),
+                        span: stmt.span,
+                        declared,
+                    })?,
+                    None => {
+                        let symbol = self.symbol_table.define(name);
+                        self.store_symbol(&symbol)?;
+                    }
+                }
+                self.compile_loop_body(start, exit, 2, body)?;
+            }
+            StmtKind::Break => {
+                let pops = self.current_scope_mut().loops.last().unwrap().pops;
+                if pops > 0 {
+                    self.emit(Opcode::Drop, &[pops])?;
+                }
+                let position = self.emit(Opcode::Jump, &[9999])?;
+                let scope = self.current_scope_mut();
+                scope.loops.last_mut().unwrap().breaks.push(position);
+            }
+            StmtKind::Continue => {
+                let start = self.current_scope_mut().loops.last().unwrap().start;
+                self.emit(Opcode::Jump, &[start])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_binding(
+        &mut self,
+        name: &str,
+        expr: &Expr,
+        doc: &Option<String>,
+        span: Span,
+        constant: Option<Span>,
+    ) -> Result<(), RuntimeError> {
+        if let Some(declared) = self.symbol_table.constant(name) {
+            self.compile_expr(expr)?;
+            return self.raise(RuntimeError::ConstantRebinding {
+                name: name.to_string(),
+                span,
+                declared,
+            });
+        }
+        let symbol = match constant {
+            Some(declared) => self.symbol_table.define_const(name, declared),
+            None => self.symbol_table.define(name),
+        };
+        match &expr.kind {
+            ExprKind::Function(params, body) => {
+                let span = mem::replace(&mut self.span, expr.span);
+                let compiled = self.compile_function(params, body, doc.clone());
+                self.span = span;
+                compiled?
+            }
+            _ => self.compile_expr(expr)?,
+        }
+        self.store_symbol(&symbol)?;
+        Ok(())
+    }
+
+    fn compile_loop_body(
+        &mut self,
+        start: usize,
+        exit: usize,
+        pops: usize,
+        body: &BlockStmt,
+    ) -> Result<(), RuntimeError> {
+        self.current_scope_mut().loops.push(Loop {
+            start,
+            pops,
+            breaks: vec![exit],
+        });
+        let compiled = self.compile_stmts(body);
+        let finished = self.current_scope_mut().loops.pop().unwrap();
+        compiled?;
+        self.emit(Opcode::Jump, &[start])?;
+        let end = self.current_instructions().len();
+        for position in finished.breaks {
+            self.change_operand(position, end)?;
+        }
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), RuntimeError> {
+        let span = mem::replace(&mut self.span, expr.span);
+        let compiled =
+            stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || self.compile_expr_kind(expr));
+        self.span = span;
+        compiled
+    }
+
+    fn compile_expr_kind(&mut self, expr: &Expr) -> Result<(), RuntimeError> {
+        match &expr.kind {
+            ExprKind::Ident(Ident(name)) => {
+                let symbol = match self.symbol_table.resolve(name) {
+                    Some(symbol) => symbol,
+                    None => self.symbol_table.define_forward(name),
+                };
+                self.load_symbol(&symbol)?;
+            }
+            ExprKind::Int(value) => {
+                let index = self.add_constant(Object::Int(*value))?;
+                self.emit(Opcode::Constant, &[index])?;
+            }
+            ExprKind::Float(value) => {
+                let index = self.add_constant(Object::Float(*value))?;
+                self.emit(Opcode::Constant, &[index])?;
+            }
+            ExprKind::BigInt(value) => {
+                let index = self.add_constant(Object::BigInt(value.clone()))?;
+                self.emit(Opcode::Constant, &[index])?;
+            }
+            ExprKind::Bool(true) => {
+                self.emit(Opcode::True, &[])?;
+            }
+            ExprKind::Bool(false) => {
+                self.emit(Opcode::False, &[])?;
+            }
+            ExprKind::String(value) => {
+                let index = self.add_constant(Object::String(value.clone()))?;
+                self.emit(Opcode::Constant, &[index])?;
+            }
+            ExprKind::Template(parts) => {
+                for part in parts {
+                    match part {
+                        TemplatePart::Literal(literal) => {
+                            let index = self.add_constant(Object::String(literal.clone()))?;
+                            self.emit(Opcode::Constant, &[index])?;
+                        }
+                        TemplatePart::Expr(expr) => self.compile_expr(expr)?,
+                    }
+                }
+                self.emit(Opcode::Template, &[parts.len()])?;
+            }
+            ExprKind::Prefix(prefix, right) => {
+                self.compile_expr(right)?;
+                match prefix {
+                    Prefix::Minus => self.emit(Opcode::Minus, &[])?,
+                    Prefix::Bang => self.emit(Opcode::Bang, &[])?,
+                };
+            }
+            ExprKind::Infix(infix @ (Infix::And | Infix::Or), left, right) => {
+                self.compile_expr(left)?;
+                let op = match infix {
+                    Infix::And => Opcode::JumpNotTruthyOrPop,
+                    _ => Opcode::JumpTruthyOrPop,
+                };
+                let jump = self.emit(op, &[9999])?;
+                self.compile_expr(right)?;
+                let after_right = self.current_instructions().len();
+                self.change_operand(jump, after_right)?;
+            }
+            ExprKind::Infix(infix, left, right) => {
+                self.compile_expr(left)?;
+                self.compile_expr(right)?;
+                self.emit(infix_opcode(infix), &[])?;
+            }
+            ExprKind::If(cond, cons, alt) => {
+                self.compile_expr(cond)?;
+                let jump_not_truthy = self.emit(Opcode::JumpNotTruthy, &[9999])?;
+                self.compile_block(cons)?;
+                let jump = self.emit(Opcode::Jump, &[9999])?;
+                let after_cons = self.current_instructions().len();
+                self.change_operand(jump_not_truthy, after_cons)?;
+                match alt {
+                    Some(alt) => self.compile_block(alt)?,
+                    None => {
+                        self.emit(Opcode::Null, &[])?;
+                    }
+                }
+                let after_alt = self.current_instructions().len();
+                self.change_operand(jump, after_alt)?;
+            }
+            ExprKind::Function(params, body) => self.compile_function(params, body, None)?,
+            ExprKind::Call(func, args) => {
+                self.compile_expr(func)?;
+                for arg in args {
+                    self.compile_expr(arg)?;
+                }
+                self.emit(Opcode::Call, &[args.len()])?;
+            }
+            ExprKind::Array(elements) => {
+                for element in elements {
+                    self.compile_expr(element)?;
+                }
+                self.emit(Opcode::Array, &[elements.len()])?;
+            }
+            ExprKind::Index(left, index) => {
+                self.compile_expr(left)?;
+                self.compile_expr(index)?;
+                self.emit(Opcode::Index, &[])?;
+            }
+            ExprKind::Hash(pairs) => {
+                for (key, value) in pairs {
+                    self.compile_expr(key)?;
+                    self.compile_expr(value)?;
+                }
+                self.emit(Opcode::Hash, &[pairs.len()])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_function(
+        &mut self,
+        params: &[Ident],
+        body: &BlockStmt,
+        doc: Option<String>,
+    ) -> Result<(), RuntimeError> {
+        self.enter_scope();
+        let mut names = HashSet::new();
+        declared_names(body, &mut names);
+        self.symbol_table.set_pending(names);
+        for Ident(param) in params {
+            self.symbol_table.define(param);
+        }
+        self.compile_stmts(body)?;
+        if self.is_last_instruction(Opcode::Pop) {
+            self.replace_last_pop_with_return();
+        }
+        if !self.is_last_instruction(Opcode::ReturnValue) {
+            self.emit(Opcode::Return, &[])?;
+        }
+        let free_symbols = self.symbol_table.free_symbols().to_vec();
+        let num_locals = self.symbol_table.num_definitions();
+        let CompilationScope {
+            instructions,
+            spans,
+            errors,
+            ..
+        } = self.leave_scope();
+        for symbol in free_symbols.iter() {
+            match symbol.scope {
+                SymbolScope::Local => self.emit(Opcode::CaptureLocal, &[symbol.index])?,
+                SymbolScope::Free => self.emit(Opcode::CaptureFree, &[symbol.index])?,
+                _ => unreachable!("{:?} symbol cannot be captured", symbol.scope),
+            };
+        }
+        let func = CompiledFunction {
+            instructions,
+            num_locals,
+            num_params: params.len(),
+            doc,
+            span: self.span,
+            spans,
+            errors,
+        };
+        let index = self.add_constant(Object::CompiledFunction(Rc::new(func)))?;
+        self.emit(Opcode::Closure, &[index, free_symbols.len()])?;
+        Ok(())
+    }
+
+    fn compile_block(&mut self, stmts: &BlockStmt) -> Result<(), RuntimeError> {
+        self.compile_stmts(stmts)?;
+        if self.is_last_instruction(Opcode::Pop) {
+            self.remove_last_pop();
+        } else if !self.is_last_instruction(Opcode::ReturnValue) {
+            self.emit(Opcode::Null, &[])?;
+        }
+        Ok(())
+    }
+
+    fn raise(&mut self, err: RuntimeError) -> Result<(), RuntimeError> {
+        let scope = self.current_scope_mut();
+        scope.errors.push(err);
+        let index = scope.errors.len() - 1;
+        self.emit(Opcode::Raise, &[index])?;
+        Ok(())
+    }
+
+    fn load_symbol(&mut self, symbol: &Symbol) -> Result<(), RuntimeError> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(Opcode::GetGlobal, &[symbol.index])?,
+            SymbolScope::Local => self.emit(Opcode::GetLocal, &[symbol.index])?,
+            SymbolScope::Builtin => self.emit(Opcode::GetBuiltin, &[symbol.index])?,
+            SymbolScope::Free => self.emit(Opcode::GetFree, &[symbol.index])?,
+        };
+        Ok(())
+    }
+
+    fn store_symbol(&mut self, symbol: &Symbol) -> Result<(), RuntimeError> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(Opcode::SetGlobal, &[symbol.index])?,
+            SymbolScope::Local => self.emit(Opcode::SetLocal, &[symbol.index])?,
+            SymbolScope::Free => self.emit(Opcode::SetFree, &[symbol.index])?,
+            SymbolScope::Builtin => unreachable!("builtin symbol cannot be assigned"),
+        };
+        Ok(())
+    }
+
+    fn add_constant(&mut self, object: Object) -> Result<usize, RuntimeError> {
+        let key = HashKey::try_from(&object).ok();
+        if let Some(&index) = key.as_ref().and_then(|key| self.constant_indices.get(key)) {
+            return Ok(index);
+        }
+        let index = self.constants.len();
+        self.check_operand(Opcode::Constant, 0, index)?;
+        self.constants.push(object);
+        if let Some(key) = key {
+            self.constant_indices.insert(key, index);
+        }
+        Ok(index)
+    }
+
+    fn check_operand(&self, op: Opcode, i: usize, operand: usize) -> Result<(), RuntimeError> {
+        let limit = operand_limit(op.operand_widths()[i]);
+        if operand < limit {
+            return Ok(());
+        }
+        Err(RuntimeError::BytecodeLimit {
+            what: op.operand_names()[i].to_string(),
+            limit,
+            span: self.span,
+        })
+    }
+
+    fn emit(&mut self, op: Opcode, operands: &[usize]) -> Result<usize, RuntimeError> {
+        for (i, operand) in operands.iter().enumerate() {
+            self.check_operand(op, i, *operand)?;
+        }
+        let instruction = make(op, operands);
+        let span = self.span;
+        let scope = self.current_scope_mut();
+        let position = scope.instructions.len();
+        scope.instructions.extend(instruction);
+        if scope.spans.last().map(|(_, last)| *last) != Some(span) {
+            scope.spans.push((position, span));
+        }
+        scope.previous_instruction = scope.last_instruction;
+        scope.last_instruction = Some(EmittedInstruction {
+            opcode: op,
+            position,
+        });
+        Ok(position)
+    }
+
+    fn current_scope_mut(&mut self) -> &mut CompilationScope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn current_instructions(&self) -> &Instructions {
+        &self.scopes.last().unwrap().instructions
+    }
+
+    fn is_last_instruction(&self, op: Opcode) -> bool {
+        matches!(
+            self.scopes.last().unwrap().last_instruction,
+            Some(EmittedInstruction { opcode, .. }) if opcode == op
+        )
+    }
+
+    fn remove_last_pop(&mut self) {
+        let scope = self.current_scope_mut();
+        let position = scope.last_instruction.unwrap().position;
+        scope.instructions.truncate(position);
+        scope.spans.retain(|(start, _)| *start < position);
+        scope.last_instruction = scope.previous_instruction;
+    }
+
+    fn replace_last_pop_with_return(&mut self) {
+        let scope = self.current_scope_mut();
+        let position = scope.last_instruction.unwrap().position;
+        scope.instructions[position] = Opcode::ReturnValue as u8;
+        scope.last_instruction = Some(EmittedInstruction {
+            opcode: Opcode::ReturnValue,
+            position,
+        });
+    }
+
+    fn change_operand(&mut self, position: usize, operand: usize) -> Result<(), RuntimeError> {
+        let op = Opcode::try_from(self.current_instructions()[position]).unwrap();
+        self.check_operand(op, 0, operand)?;
+        let scope = self.current_scope_mut();
+        let instruction = make(op, &[operand]);
+        scope.instructions[position..position + instruction.len()].copy_from_slice(&instruction);
+        Ok(())
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(CompilationScope::default());
+        let outer = std::mem::take(&mut self.symbol_table);
+        self.symbol_table = SymbolTable::new_enclosed(outer);
+    }
+
+    fn leave_scope(&mut self) -> CompilationScope {
+        let scope = self.scopes.pop().unwrap();
+        let symbol_table = std::mem::take(&mut self.symbol_table);
+        self.symbol_table = symbol_table.into_outer().unwrap();
+        scope
+    }
+}
+
+fn infix_opcode(infix: &Infix) -> Opcode {
+    match infix {
+        Infix::Plus => Opcode::Add,
+        Infix::Minus => Opcode::Sub,
+        Infix::Asterisk => Opcode::Mul,
+        Infix::Slash => Opcode::Div,
+        Infix::Percent => Opcode::Mod,
+        Infix::Power => Opcode::Pow,
+        Infix::Lt => Opcode::LessThan,
+        Infix::Gt => Opcode::GreaterThan,
+        Infix::Le => Opcode::LessEqual,
+        Infix::Ge => Opcode::GreaterEqual,
+        Infix::Eq => Opcode::Equal,
+        Infix::Ne => Opcode::NotEqual,
+        Infix::And | Infix::Or => unreachable!(),
+    }
+}
+
+fn declared_names(stmts: &BlockStmt, names: &mut HashSet<String>) {
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Let(Ident(name), expr, _) | StmtKind::Const(Ident(name), expr, _) => {
+                names.insert(name.clone());
+                expr_declared_names(expr, names);
+            }
+            StmtKind::For(Ident(name), iterable, body) => {
+                names.insert(name.clone());
+                expr_declared_names(iterable, names);
+                declared_names(body, names);
+            }
+            StmtKind::While(cond, body) => {
+                expr_declared_names(cond, names);
+                declared_names(body, names);
+            }
+            StmtKind::Assign(_, _, expr) | StmtKind::Return(expr) | StmtKind::Expr(expr) => {
+                expr_declared_names(expr, names)
+            }
+            StmtKind::Break | StmtKind::Continue => {}
+        }
+    }
+}
+
+fn expr_declared_names(expr: &Expr, names: &mut HashSet<String>) {
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || match &expr.kind {
+        ExprKind::If(cond, cons, alt) => {
+            expr_declared_names(cond, names);
+            declared_names(cons, names);
+            if let Some(alt) = alt {
+                declared_names(alt, names);
+            }
+        }
+        ExprKind::Prefix(_, right) => expr_declared_names(right, names),
+        ExprKind::Infix(_, left, right) | ExprKind::Index(left, right) => {
+            expr_declared_names(left, names);
+            expr_declared_names(right, names);
+        }
+        ExprKind::Call(func, args) => {
+            expr_declared_names(func, names);
+            for arg in args {
+                expr_declared_names(arg, names);
+            }
+        }
+        ExprKind::Array(elements) => {
+            for element in elements {
+                expr_declared_names(element, names);
+            }
+        }
+        ExprKind::Hash(pairs) => {
+            for (key, value) in pairs {
+                expr_declared_names(key, names);
+                expr_declared_names(value, names);
+            }
+        }
+        ExprKind::Template(parts) => {
+            for part in parts {
+                if let TemplatePart::Expr(expr) = part {
+                    expr_declared_names(expr, names);
+                }
+            }
+        }
+        _ => {}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{code::disassemble, lexer::Lexer, parser::Parser};
+
+    fn compile(input: &str) -> Result<Bytecode, RuntimeError> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+        let mut compiler = Compiler::new();
+        compiler.compile(&program)?;
+        Ok(compiler.bytecode())
+    }
+
+    fn function(bytecode: &Bytecode, index: usize) -> String {
+        match &bytecode.constants[index] {
+            Object::CompiledFunction(func) => disassemble(&func.instructions),
+            object => panic!("{} is not compiled function", object),
+        }
+    }
+
+    #[test]
+    fn test_compile_dedups_constants() {
+        let bytecode = compile("1 + 2; 1").unwrap();
+        assert_eq!(
+            disassemble(&bytecode.instructions),
+            "0000 Constant 0\n0003 Constant 1\n0006 Add\n0007 Pop\n0008 Constant 0\n0011 Pop\n"
+        );
+        assert_eq!(bytecode.constants, vec![Object::Int(1), Object::Int(2)]);
+    }
+
+    #[test]
+    fn test_compile_captured_assignment() {
+        let bytecode = compile("let f = fn(a) { fn() { a += 1 } };").unwrap();
+        assert_eq!(
+            function(&bytecode, 1),
+            "0000 GetFree 0\n0002 Constant 0\n0005 Add\n0006 SetFree 0\n0008 Return\n"
+        );
+        assert_eq!(
+            function(&bytecode, 2),
+            "0000 CaptureLocal 0\n0002 Closure 1 1\n0006 ReturnValue\n"
+        );
+        assert_eq!(
+            disassemble(&bytecode.instructions),
+            "0000 Closure 2 0\n0004 SetGlobal 0\n0007 Null\n0008 Pop\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_forward_global() {
+        let bytecode = compile("let f = fn() { g }; let g = 1;").unwrap();
+        assert_eq!(
+            function(&bytecode, 0),
+            "0000 GetGlobal 1\n0003 ReturnValue\n"
+        );
+        assert_eq!(
+            disassemble(&bytecode.instructions),
+            "0000 Closure 0 0\n0004 SetGlobal 0\n0007 Constant 1\n0010 SetGlobal 1\n0013 Null\n0014 Pop\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_operand_overflow() {
+        let args = vec!["1"; 256].join(", ");
+        let err = compile(&format!("puts({})", args)).unwrap_err();
+        assert_eq!(err.code(), "E1019");
+        assert_eq!(
+            err.to_string(),
+            "too many call arguments for the vm engine (limit 256)"
+        );
+        let locals = (0..257)
+            .map(|i| format!("let {} = {};", name(i), i))
+            .collect::<String>();
+        let err = compile(&format!("fn() {{ {} }}", locals)).unwrap_err();
+        assert_eq!(err.code(), "E1019");
+    }
+    fn name(i: usize) -> String {
+        let letter = |n: usize| char::from(b'a' + n as u8);
+        format!("v{}{}", letter(i / 26), letter(i % 26))
+    }
+}
@@ -0,0 +1,134 @@
+use crate::token::Span;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn render(&self, source: &str) -> String {
+        let mut out = match self.code {
+            Some(ref code) => format!("{}[{}]: {}\n", self.severity, code, self.message),
+            None => format!("{}: {}\n", self.severity, self.message),
+        };
+        if self.span == Span::default() {
+            return out;
+        }
+
+        let mut marks = vec![(self.span, '^', "")];
+        for label in self.labels.iter() {
+            marks.push((label.span, '-', &label.message));
+        }
+        marks.retain(|(span, _, _)| source.is_char_boundary(span.start));
+        marks.sort_by_key(|(span, _, _)| span.start);
+
+        let width = marks
+            .iter()
+            .map(|(span, _, _)| span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+
+        out.push_str(&format!("{}--> {}\n", gutter, self.span));
+        out.push_str(&format!("{} |\n", gutter));
+        let mut last_line = None;
+        for (span, mark, message) in marks {
+            let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[span.start..]
+                .find('\n')
+                .map_or(source.len(), |i| span.start + i);
+            if last_line != Some(span.line) {
+                if matches!(last_line, Some(line) if span.line > line + 1) {
+                    out.push_str("...\n");
+                }
+                out.push_str(&format!(
+                    "{:>width$} | {}\n",
+                    span.line,
+                    &source[line_start..line_end],
+                    width = width
+                ));
+                last_line = Some(span.line);
+            }
+            let padding = source[line_start..span.start].chars().count();
+            let end = match span.end.clamp(span.start, line_end) {
+                end if source.is_char_boundary(end) => end,
+                _ => line_end,
+            };
+            let length = source[span.start..end].chars().count().max(1);
+            let underline = format!("{}{}", " ".repeat(padding), mark.to_string().repeat(length));
+            if message.is_empty() {
+                out.push_str(&format!("{} | {}\n", gutter, underline));
+            } else {
+                out.push_str(&format!("{} | {} {}\n", gutter, underline, message));
+            }
+        }
+        for note in self.notes.iter() {
+            out.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        out
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
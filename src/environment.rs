@@ -1,9 +1,15 @@
-use crate::object::Object;
+use crate::{object::Object, token::Span};
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
+struct Binding {
+    value: Object,
+    constant: Option<Span>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Environment {
-    store: HashMap<String, Object>,
+    store: HashMap<String, Binding>,
     outer: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -21,7 +27,7 @@ impl Environment {
 
     pub fn get(&mut self, name: String) -> Option<Object> {
         match self.store.get(&name) {
-            Some(val) => Some(val.clone()),
+            Some(binding) => Some(binding.value.clone()),
             None => match self.outer {
                 Some(ref outer) => outer.borrow_mut().get(name),
                 None => None,
@@ -30,6 +36,52 @@ impl Environment {
     }
 
     pub fn set(&mut self, name: String, val: &Object) {
-        self.store.insert(name, val.clone());
+        self.store.insert(
+            name,
+            Binding {
+                value: val.clone(),
+                constant: None,
+            },
+        );
+    }
+
+    pub fn declare(
+        &mut self,
+        name: String,
+        val: &Object,
+        constant: Option<Span>,
+    ) -> Result<(), Span> {
+        if let Some(Binding {
+            constant: Some(declared),
+            ..
+        }) = self.store.get(&name)
+        {
+            return Err(*declared);
+        }
+        self.store.insert(
+            name,
+            Binding {
+                value: val.clone(),
+                constant,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn assign(&mut self, name: String, val: &Object) -> Result<bool, Span> {
+        match self.store.get_mut(&name) {
+            Some(Binding {
+                constant: Some(declared),
+                ..
+            }) => Err(*declared),
+            Some(binding) => {
+                binding.value = val.clone();
+                Ok(true)
+            }
+            None => match self.outer {
+                Some(ref outer) => outer.borrow_mut().assign(name, val),
+                None => Ok(false),
+            },
+        }
     }
 }
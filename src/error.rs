@@ -0,0 +1,245 @@
+use crate::{
+    ast::{Infix, Prefix},
+    diagnostics::Diagnostic,
+    token::Span,
+};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    TypeMismatch {
+        left: String,
+        infix: Infix,
+        right: String,
+        span: Span,
+    },
+    UnknownPrefixOperator {
+        prefix: Prefix,
+        right: String,
+        span: Span,
+    },
+    UnknownInfixOperator {
+        left: String,
+        infix: Infix,
+        right: String,
+        span: Span,
+    },
+    UndefinedIdentifier {
+        name: String,
+        span: Span,
+    },
+    ArityMismatch {
+        expected: usize,
+        given: usize,
+        span: Span,
+        defined: Option<Span>,
+    },
+    NotCallable {
+        callee: String,
+        span: Span,
+    },
+    IndexNotSupported {
+        left: String,
+        span: Span,
+    },
+    UnusableHashKey {
+        key: String,
+        span: Span,
+    },
+    UnsupportedArgument {
+        name: String,
+        arg: String,
+        span: Span,
+    },
+    DivisionByZero {
+        span: Span,
+    },
+    StackOverflow {
+        span: Span,
+    },
+    RecursionLimit {
+        limit: usize,
+        span: Span,
+    },
+    NotIterable {
+        iterable: String,
+        span: Span,
+    },
+    UndeclaredAssignment {
+        name: String,
+        span: Span,
+    },
+    ConstantRebinding {
+        name: String,
+        span: Span,
+        declared: Span,
+    },
+    NegativeExponent {
+        span: Span,
+    },
+    IntegerOverflow {
+        span: Span,
+    },
+    BytecodeLimit {
+        what: String,
+        limit: usize,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    pub name: Option<String>,
+    pub call_site: Span,
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "in `{}`, called at {}", name, self.call_site),
+            None => write!(f, "in anonymous function, called at {}", self.call_site),
+        }
+    }
+}
+
+impl RuntimeError {
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::TypeMismatch { span, .. }
+            | RuntimeError::UnknownPrefixOperator { span, .. }
+            | RuntimeError::UnknownInfixOperator { span, .. }
+            | RuntimeError::UndefinedIdentifier { span, .. }
+            | RuntimeError::ArityMismatch { span, .. }
+            | RuntimeError::NotCallable { span, .. }
+            | RuntimeError::IndexNotSupported { span, .. }
+            | RuntimeError::UnusableHashKey { span, .. }
+            | RuntimeError::UnsupportedArgument { span, .. }
+            | RuntimeError::DivisionByZero { span }
+            | RuntimeError::StackOverflow { span }
+            | RuntimeError::RecursionLimit { span, .. }
+            | RuntimeError::NotIterable { span, .. }
+            | RuntimeError::UndeclaredAssignment { span, .. }
+            | RuntimeError::ConstantRebinding { span, .. }
+            | RuntimeError::NegativeExponent { span }
+            | RuntimeError::IntegerOverflow { span }
+            | RuntimeError::BytecodeLimit { span, .. } => *span,
+        }
+    }
+
+    pub fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            RuntimeError::TypeMismatch { span, .. }
+            | RuntimeError::UnknownPrefixOperator { span, .. }
+            | RuntimeError::UnknownInfixOperator { span, .. }
+            | RuntimeError::UndefinedIdentifier { span, .. }
+            | RuntimeError::ArityMismatch { span, .. }
+            | RuntimeError::NotCallable { span, .. }
+            | RuntimeError::IndexNotSupported { span, .. }
+            | RuntimeError::UnusableHashKey { span, .. }
+            | RuntimeError::UnsupportedArgument { span, .. }
+            | RuntimeError::DivisionByZero { span }
+            | RuntimeError::StackOverflow { span }
+            | RuntimeError::RecursionLimit { span, .. }
+            | RuntimeError::NotIterable { span, .. }
+            | RuntimeError::UndeclaredAssignment { span, .. }
+            | RuntimeError::ConstantRebinding { span, .. }
+            | RuntimeError::NegativeExponent { span }
+            | RuntimeError::IntegerOverflow { span }
+            | RuntimeError::BytecodeLimit { span, .. } => *span = new_span,
+        }
+        self
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::TypeMismatch { .. } => "E1001",
+            RuntimeError::UnknownPrefixOperator { .. } => "E1002",
+            RuntimeError::UnknownInfixOperator { .. } => "E1003",
+            RuntimeError::UndefinedIdentifier { .. } => "E1004",
+            RuntimeError::ArityMismatch { .. } => "E1005",
+            RuntimeError::NotCallable { .. } => "E1006",
+            RuntimeError::IndexNotSupported { .. } => "E1007",
+            RuntimeError::UnusableHashKey { .. } => "E1008",
+            RuntimeError::UnsupportedArgument { .. } => "E1009",
+            RuntimeError::DivisionByZero { .. } => "E1010",
+            RuntimeError::StackOverflow { .. } => "E1011",
+            RuntimeError::RecursionLimit { .. } => "E1012",
+            RuntimeError::NotIterable { .. } => "E1013",
+            RuntimeError::UndeclaredAssignment { .. } => "E1014",
+            RuntimeError::ConstantRebinding { .. } => "E1016",
+            RuntimeError::NegativeExponent { .. } => "E1017",
+            RuntimeError::IntegerOverflow { .. } => "E1018",
+            RuntimeError::BytecodeLimit { .. } => "E1019",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string(), self.span()).with_code(self.code());
+        match self {
+            RuntimeError::ArityMismatch {
+                defined: Some(defined),
+                ..
+            } => diagnostic.with_label(*defined, "function defined here"),
+            RuntimeError::ConstantRebinding { name, declared, .. } => diagnostic.with_label(
+                *declared,
+                &format!("`{}` declared as a constant here", name),
+            ),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::TypeMismatch {
+                left, infix, right, ..
+            } => write!(f, "type mismatch: {} {} {}", left, infix, right),
+            RuntimeError::UnknownPrefixOperator { prefix, right, .. } => {
+                write!(f, "unknown operator: {} {}", prefix, right)
+            }
+            RuntimeError::UnknownInfixOperator {
+                left, infix, right, ..
+            } => write!(f, "unknown operator: {} {} {}", left, infix, right),
+            RuntimeError::UndefinedIdentifier { name, .. } => {
+                write!(f, "identifier not found: {}", name)
+            }
+            RuntimeError::ArityMismatch {
+                expected, given, ..
+            } => write!(
+                f,
+                "wrong number of arguments: {} expected but {} given",
+                expected, given
+            ),
+            RuntimeError::NotCallable { callee, .. } => {
+                write!(f, "{} is not valid function", callee)
+            }
+            RuntimeError::IndexNotSupported { left, .. } => {
+                write!(f, "index operator not supported: {}", left)
+            }
+            RuntimeError::UnusableHashKey { key, .. } => write!(f, "unusable as hash key: {}", key),
+            RuntimeError::UnsupportedArgument { name, arg, .. } => {
+                write!(f, "argument to `{}` not supported, got {}", name, arg)
+            }
+            RuntimeError::DivisionByZero { .. } => write!(f, "division by zero"),
+            RuntimeError::StackOverflow { .. } => write!(f, "stack overflow"),
+            RuntimeError::RecursionLimit { limit, .. } => {
+                write!(f, "maximum recursion depth exceeded ({} calls)", limit)
+            }
+            RuntimeError::NotIterable { iterable, .. } => {
+                write!(f, "cannot iterate over {}", iterable)
+            }
+            RuntimeError::UndeclaredAssignment { name, .. } => {
+                write!(f, "cannot assign to undeclared variable `{}`", name)
+            }
+            RuntimeError::ConstantRebinding { name, .. } => {
+                write!(f, "cannot rebind constant `{}`", name)
+            }
+            RuntimeError::NegativeExponent { .. } => write!(f, "negative exponent"),
+            RuntimeError::IntegerOverflow { .. } => write!(f, "integer overflow"),
+            RuntimeError::BytecodeLimit { what, limit, .. } => {
+                write!(f, "too many {} for the vm engine (limit {})", what, limit)
+            }
+        }
+    }
+}
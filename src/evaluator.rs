@@ -1,225 +1,660 @@
 use crate::{
-    ast::{BlockStmt, Expr, Ident, Infix, Prefix, Program, Stmt},
+    ast::{BlockStmt, Expr, ExprKind, Ident, Infix, Prefix, Program, Stmt, StmtKind, TemplatePart},
+    builtins::get_builtin,
     environment::Environment,
-    object::Object,
+    error::{RuntimeError, StackFrame},
+    object::{Function, HashKey, Object},
+    token::Span,
+    RED_ZONE, STACK_SEGMENT,
 };
-use std::{cell::RefCell, rc::Rc};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+enum Tail {
+    Value(Object),
+    Call(Rc<Function>, Vec<Object>, Span),
+}
 
 #[derive(Debug)]
 pub struct Evaluator {
     env: Rc<RefCell<Environment>>,
+    max_depth: usize,
+    call_stack: Vec<StackFrame>,
+    stack_trace: Vec<StackFrame>,
 }
 
 impl Evaluator {
     pub fn new(env: Rc<RefCell<Environment>>) -> Self {
-        Evaluator { env }
+        Evaluator {
+            env,
+            max_depth: DEFAULT_MAX_DEPTH,
+            call_stack: Vec::new(),
+            stack_trace: Vec::new(),
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        let object = self.env.borrow_mut().get(name.to_string());
+        object.or_else(|| get_builtin(name))
+    }
+
+    pub fn stack_trace(&self) -> &[StackFrame] {
+        &self.stack_trace
     }
 
-    pub fn eval(&mut self, program: Program) -> Option<Object> {
-        let mut result = None;
+    pub fn eval(&mut self, program: Program) -> Result<Object, RuntimeError> {
+        self.call_stack.clear();
+        self.stack_trace.clear();
+        let mut result = Object::Null;
         for stmt in program {
-            match self.eval_stmt(stmt) {
-                Some(Object::Return(object)) => return Some(*object),
-                object @ Some(Object::Error(_)) => return object,
-                object => result = object,
+            match self.eval_stmt(stmt)? {
+                Tail::Value(Object::Return(object)) => return Ok(*object),
+                Tail::Value(object) => result = object,
+                Tail::Call(func, objects, span) => return self.call_function(func, objects, span),
             }
         }
-        result
+        Ok(result)
     }
 
-    fn eval_stmts(&mut self, stmts: BlockStmt) -> Option<Object> {
-        let mut result = None;
-        for stmt in stmts {
-            match self.eval_stmt(stmt) {
-                object @ (Some(Object::Return(_)) | Some(Object::Error(_))) => return object,
-                object => result = object,
+    fn eval_stmts(&mut self, stmts: BlockStmt, tail: bool) -> Result<Tail, RuntimeError> {
+        let mut result = Object::Null;
+        let len = stmts.len();
+        for (i, stmt) in stmts.into_iter().enumerate() {
+            let evaluated = match stmt.kind {
+                StmtKind::Expr(expr) if tail && i == len - 1 => self.eval_tail_expr(expr)?,
+                kind => self.eval_stmt(Stmt { kind, ..stmt })?,
+            };
+            match evaluated {
+                Tail::Value(Object::Return(_) | Object::Break | Object::Continue)
+                | Tail::Call(..) => return Ok(evaluated),
+                Tail::Value(object) => result = object,
             }
         }
-        result
+        Ok(Tail::Value(result))
     }
 
-    fn eval_stmt(&mut self, stmt: Stmt) -> Option<Object> {
-        match stmt {
-            Stmt::Expr(expr) => self.eval_expr(expr),
-            Stmt::Return(expr) => {
-                let object = self.eval_expr(expr)?;
-                if is_error(&object) {
-                    return Some(object);
+    fn eval_stmt(&mut self, stmt: Stmt) -> Result<Tail, RuntimeError> {
+        match stmt.kind {
+            StmtKind::Expr(Expr {
+                kind: ExprKind::If(cond, cons, alt),
+                ..
+            }) => self.eval_if(*cond, cons, alt, false),
+            StmtKind::Expr(expr) => self.eval_expr(expr).map(Tail::Value),
+            StmtKind::Return(expr) => match self.eval_tail_expr(expr)? {
+                Tail::Value(object) => Ok(Tail::Value(Object::Return(Box::new(object)))),
+                call => Ok(call),
+            },
+            StmtKind::Let(Ident(name), expr, doc) => self
+                .eval_binding(name, expr, doc, stmt.span, None)
+                .map(Tail::Value),
+            StmtKind::Const(Ident(name), expr, doc) => self
+                .eval_binding(name, expr, doc, stmt.span, Some(stmt.span))
+                .map(Tail::Value),
+            StmtKind::Assign(Ident(name), infix, expr) => self
+                .eval_assign(name, infix, expr, stmt.span)
+                .map(Tail::Value),
+            StmtKind::While(cond, body) => {
+                while is_truthy(self.eval_expr(cond.clone())?) {
+                    match self.eval_stmts(body.clone(), false)? {
+                        Tail::Value(Object::Break) => break,
+                        tail @ (Tail::Value(Object::Return(_)) | Tail::Call(..)) => {
+                            return Ok(tail)
+                        }
+                        _ => {}
+                    }
                 }
-                Some(Object::Return(Box::new(object)))
+                Ok(Tail::Value(Object::Null))
             }
-            Stmt::Let(name, expr) => {
-                let Ident(name) = name;
-                let object = self.eval_expr(expr)?;
-                if is_error(&object) {
-                    return Some(object);
+            StmtKind::For(Ident(name), iterable, body) => {
+                let span = iterable.span;
+                let iterable = self.eval_expr(iterable)?;
+                for object in iterate(iterable).map_err(|err| err.with_span(span))? {
+                    let declared = self.env.borrow_mut().declare(name.clone(), &object, None);
+                    if let Err(declared) = declared {
+                        return Err(RuntimeError::ConstantRebinding {
+                            name,
+                            span: stmt.span,
+                            declared,
+                        });
+                    }
+                    match self.eval_stmts(body.clone(), false)? {
+                        Tail::Value(Object::Break) => break,
+                        tail @ (Tail::Value(Object::Return(_)) | Tail::Call(..)) => {
+                            return Ok(tail)
+                        }
+                        _ => {}
+                    }
                 }
-                self.env.borrow_mut().set(name, &object);
-                None
+                Ok(Tail::Value(Object::Null))
             }
+            StmtKind::Break => Ok(Tail::Value(Object::Break)),
+            StmtKind::Continue => Ok(Tail::Value(Object::Continue)),
         }
     }
 
-    fn eval_expr(&mut self, expr: Expr) -> Option<Object> {
-        match expr {
-            Expr::Ident(name) => {
+    fn eval_assign(
+        &mut self,
+        name: String,
+        infix: Option<Infix>,
+        expr: Expr,
+        span: Span,
+    ) -> Result<Object, RuntimeError> {
+        let current = match infix {
+            Some(_) => Some(self.env.borrow_mut().get(name.clone()).ok_or(
+                RuntimeError::UndeclaredAssignment {
+                    name: name.clone(),
+                    span,
+                },
+            )?),
+            None => None,
+        };
+        let mut object = self.eval_expr(expr)?;
+        if let (Some(infix), Some(current)) = (infix, current) {
+            object = eval_infix_expr(infix, current, object).map_err(|err| err.with_span(span))?;
+        }
+        match self.env.borrow_mut().assign(name.clone(), &object) {
+            Ok(true) => Ok(Object::Null),
+            Ok(false) => Err(RuntimeError::UndeclaredAssignment { name, span }),
+            Err(declared) => Err(RuntimeError::ConstantRebinding {
+                name,
+                span,
+                declared,
+            }),
+        }
+    }
+
+    fn eval_binding(
+        &mut self,
+        name: String,
+        expr: Expr,
+        doc: Option<String>,
+        span: Span,
+        constant: Option<Span>,
+    ) -> Result<Object, RuntimeError> {
+        let object = match self.eval_expr(expr)? {
+            Object::Function(func) if func.name.is_none() => Object::Function(Rc::new(Function {
+                name: Some(name.clone()),
+                doc,
+                ..(*func).clone()
+            })),
+            object => object,
+        };
+        let declared = self
+            .env
+            .borrow_mut()
+            .declare(name.clone(), &object, constant);
+        match declared {
+            Ok(()) => Ok(Object::Null),
+            Err(declared) => Err(RuntimeError::ConstantRebinding {
+                name,
+                span,
+                declared,
+            }),
+        }
+    }
+
+    fn eval_expr(&mut self, expr: Expr) -> Result<Object, RuntimeError> {
+        stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || self.eval_expr_kind(expr))
+    }
+
+    fn eval_expr_kind(&mut self, expr: Expr) -> Result<Object, RuntimeError> {
+        let span = expr.span;
+        match expr.kind {
+            ExprKind::Ident(name) => {
                 let Ident(name) = name;
-                self.env
-                    .borrow_mut()
-                    .get(name.clone())
-                    .or(Some(Object::Error(format!(
-                        "identifier not found: {}",
-                        name,
-                    ))))
-            }
-            Expr::Int(value) => Some(Object::Int(value)),
-            Expr::Bool(value) => Some(Object::Bool(value)),
-            Expr::String(value) => Some(Object::String(value)),
-            Expr::Prefix(prefix, right) => {
-                let right = self.eval_expr(*right)?;
-                if is_error(&right) {
-                    return Some(right);
+                let object = self.env.borrow_mut().get(name.clone());
+                object
+                    .or_else(|| get_builtin(&name))
+                    .ok_or(RuntimeError::UndefinedIdentifier { name, span })
+            }
+            ExprKind::Int(value) => Ok(Object::Int(value)),
+            ExprKind::BigInt(value) => Ok(Object::BigInt(value)),
+            ExprKind::Float(value) => Ok(Object::Float(value)),
+            ExprKind::Bool(value) => Ok(Object::Bool(value)),
+            ExprKind::String(value) => Ok(Object::String(value)),
+            ExprKind::Template(parts) => {
+                let mut value = String::new();
+                for part in parts {
+                    match part {
+                        TemplatePart::Literal(literal) => value.push_str(&literal),
+                        TemplatePart::Expr(expr) => {
+                            value.push_str(&self.eval_expr(expr)?.to_string())
+                        }
+                    }
                 }
-                self.eval_prefix_expr(prefix, right)
+                Ok(Object::String(value))
+            }
+            ExprKind::Prefix(prefix, right) => {
+                let right = self.eval_expr(*right)?;
+                eval_prefix_expr(prefix, right).map_err(|err| err.with_span(span))
             }
-            Expr::Infix(infix, left, right) => {
+            ExprKind::Infix(infix @ (Infix::And | Infix::Or), left, right) => {
                 let left = self.eval_expr(*left)?;
-                if is_error(&left) {
-                    return Some(left);
+                if is_truthy(left.clone()) == (infix == Infix::Or) {
+                    Ok(left)
+                } else {
+                    self.eval_expr(*right)
                 }
+            }
+            ExprKind::Infix(infix, left, right) => {
+                let left = self.eval_expr(*left)?;
                 let right = self.eval_expr(*right)?;
-                if is_error(&right) {
-                    return Some(right);
-                }
-                self.eval_infix_expr(infix, left, right)
+                eval_infix_expr(infix, left, right).map_err(|err| err.with_span(span))
             }
-            Expr::If(cond, cons, alt) => self.eval_if_expr(*cond, cons, alt),
-            Expr::Function(params, body) => Some(Object::Function(params, body, self.env.clone())),
-            Expr::Call(func, args) => {
-                let func = self.eval_expr(*func)?;
-                if is_error(&func) {
-                    return Some(func);
+            ExprKind::If(cond, cons, alt) => match self.eval_if(*cond, cons, alt, false)? {
+                Tail::Value(object) => Ok(object),
+                Tail::Call(func, objects, span) => {
+                    let object = self.call_function(func, objects, span)?;
+                    Ok(Object::Return(Box::new(object)))
                 }
-                self.eval_call_expr(func, args)
+            },
+            ExprKind::Function(params, body) => Ok(Object::Function(Rc::new(Function {
+                params,
+                body,
+                env: Rc::clone(&self.env),
+                span,
+                name: None,
+                doc: None,
+            }))),
+            ExprKind::Call(func, args) => {
+                let func = self.eval_expr(*func)?;
+                self.eval_call_expr(func, args, span)
+            }
+            ExprKind::Array(elements) => {
+                let objects = self.eval_exprs(elements)?;
+                Ok(Object::Array(objects))
             }
+            ExprKind::Index(left, index) => {
+                let left = self.eval_expr(*left)?;
+                let index = self.eval_expr(*index)?;
+                eval_index_expr(left, index).map_err(|err| err.with_span(span))
+            }
+            ExprKind::Hash(pairs) => self.eval_hash_expr(pairs),
         }
     }
 
-    fn eval_prefix_expr(&mut self, prefix: Prefix, right: Object) -> Option<Object> {
-        match prefix {
-            Prefix::Bang => match right {
-                Object::Bool(value) => Some(Object::Bool(!value)),
-                Object::Null => Some(Object::Bool(true)),
-                _ => Some(Object::Bool(false)),
-            },
-            Prefix::Minus => match right {
-                Object::Int(value) => Some(Object::Int(-value)),
-                object => Some(Object::Error(format!(
-                    "unknown operator: - {}",
-                    object.get_type()
-                ))),
-            },
+    fn eval_exprs(&mut self, exprs: Vec<Expr>) -> Result<Vec<Object>, RuntimeError> {
+        let mut objects = Vec::new();
+        for expr in exprs {
+            objects.push(self.eval_expr(expr)?);
         }
+        Ok(objects)
     }
 
-    fn eval_infix_expr(&mut self, infix: Infix, left: Object, right: Object) -> Option<Object> {
-        match (left, right) {
-            (Object::Int(left), Object::Int(right)) => match infix {
-                Infix::Plus => Some(Object::Int(left + right)),
-                Infix::Minus => Some(Object::Int(left - right)),
-                Infix::Asterisk => Some(Object::Int(left * right)),
-                Infix::Slash => Some(Object::Int(left / right)),
-                Infix::Lt => Some(Object::Bool(left < right)),
-                Infix::Gt => Some(Object::Bool(left > right)),
-                Infix::Eq => Some(Object::Bool(left == right)),
-                Infix::Ne => Some(Object::Bool(left != right)),
-            },
-            (Object::String(left), Object::String(right)) => match infix {
-                Infix::Plus => Some(Object::String(format!("{}{}", left, right))),
-                Infix::Eq => Some(Object::Bool(left == right)),
-                Infix::Ne => Some(Object::Bool(left != right)),
-                operator => Some(Object::Error(format!(
-                    "unknown operator: {} {} {}",
-                    Object::String(left).get_type(),
-                    operator,
-                    Object::String(right).get_type()
-                ))),
-            },
-            (Object::Bool(left), Object::Bool(right)) => match infix {
-                Infix::Eq => Some(Object::Bool(left == right)),
-                Infix::Ne => Some(Object::Bool(left != right)),
-                operator => Some(Object::Error(format!(
-                    "unknown operator: {} {} {}",
-                    Object::Bool(left).get_type(),
-                    operator,
-                    Object::Bool(right).get_type()
-                ))),
-            },
-            (left, right) => Some(Object::Error(format!(
-                "type mismatch: {} {} {}",
-                left.get_type(),
-                infix,
-                right.get_type()
-            ))),
+    fn eval_hash_expr(&mut self, pairs: Vec<(Expr, Expr)>) -> Result<Object, RuntimeError> {
+        let mut objects = BTreeMap::new();
+        for (key, value) in pairs {
+            let span = key.span;
+            let key = self.eval_expr(key)?;
+            let key = HashKey::try_from(&key).map_err(|err| err.with_span(span))?;
+            let value = self.eval_expr(value)?;
+            objects.insert(key, value);
         }
+        Ok(Object::Hash(objects))
     }
 
-    fn eval_if_expr(
+    fn eval_if(
         &mut self,
         cond: Expr,
         cons: BlockStmt,
         alt: Option<BlockStmt>,
-    ) -> Option<Object> {
+        tail: bool,
+    ) -> Result<Tail, RuntimeError> {
         let cond = self.eval_expr(cond)?;
-        if is_error(&cond) {
-            return Some(cond);
-        }
         if is_truthy(cond) {
-            self.eval_stmts(cons)
-        } else if alt.is_some() {
-            self.eval_stmts(alt.unwrap())
+            self.eval_stmts(cons, tail)
+        } else if let Some(alt) = alt {
+            self.eval_stmts(alt, tail)
         } else {
-            Some(Object::Null)
+            Ok(Tail::Value(Object::Null))
         }
     }
 
-    fn eval_call_expr(&mut self, func: Object, args: Vec<Expr>) -> Option<Object> {
-        let mut objects = Vec::new();
-        for arg in args.iter() {
-            let object = self.eval_expr(arg.clone())?;
-            if is_error(&object) {
-                return Some(object);
-            }
-            objects.push(object);
+    fn eval_tail_expr(&mut self, expr: Expr) -> Result<Tail, RuntimeError> {
+        let span = expr.span;
+        match expr.kind {
+            ExprKind::Call(func, args) => match self.eval_expr(*func)? {
+                Object::Function(func) => {
+                    let objects = self.eval_exprs(args)?;
+                    Ok(Tail::Call(func, objects, span))
+                }
+                func => self.eval_call_expr(func, args, span).map(Tail::Value),
+            },
+            ExprKind::If(cond, cons, alt) => self.eval_if(*cond, cons, alt, true),
+            kind => self.eval_expr(Expr { kind, span }).map(Tail::Value),
         }
-        let (params, body, env) = match func {
-            Object::Function(params, body, env) => (params, body, env),
-            object => return Some(Object::Error(format!("{} is not valid function", object))),
-        };
-        if args.len() != params.len() {
-            return Some(Object::Error(format!(
-                "wrong number of arguments: {} expected but {} given",
-                params.len(),
-                args.len(),
-            )));
+    }
+
+    fn eval_call_expr(
+        &mut self,
+        func: Object,
+        args: Vec<Expr>,
+        span: Span,
+    ) -> Result<Object, RuntimeError> {
+        let objects = self.eval_exprs(args)?;
+        match func {
+            Object::Function(func) => self.call_function(func, objects, span),
+            Object::Builtin(builtin) => (builtin.func)(objects).map_err(|err| err.with_span(span)),
+            object => Err(RuntimeError::NotCallable {
+                callee: object.to_string(),
+                span,
+            }),
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        func: Rc<Function>,
+        objects: Vec<Object>,
+        span: Span,
+    ) -> Result<Object, RuntimeError> {
+        let depth = self.call_stack.len();
+        if depth >= self.max_depth {
+            return Err(RuntimeError::RecursionLimit {
+                limit: self.max_depth,
+                span,
+            });
         }
         let current_env = Rc::clone(&self.env);
-        let mut scoped_env = Environment::new_with_outer(Rc::clone(&env));
-        for (_, (ident, object)) in params.iter().zip(objects.iter()).enumerate() {
-            let Ident(name) = ident.clone();
-            scoped_env.set(name, object);
+        let mut call = (func, objects, span);
+        let result = loop {
+            let (func, objects, span) = call;
+            if objects.len() != func.params.len() {
+                break Err(RuntimeError::ArityMismatch {
+                    expected: func.params.len(),
+                    given: objects.len(),
+                    span,
+                    defined: Some(func.span),
+                });
+            }
+            let mut scoped_env = Environment::new_with_outer(Rc::clone(&func.env));
+            for (ident, object) in func.params.iter().zip(objects.iter()) {
+                let Ident(name) = ident.clone();
+                scoped_env.set(name, object);
+            }
+            self.env = Rc::new(RefCell::new(scoped_env));
+            let frame = StackFrame {
+                name: func.name.clone(),
+                call_site: span,
+            };
+            match self.call_stack.get_mut(depth) {
+                Some(top) => *top = frame,
+                None => self.call_stack.push(frame),
+            }
+            match self.eval_stmts(func.body.clone(), true) {
+                Ok(Tail::Call(func, objects, span)) => call = (func, objects, span),
+                Ok(Tail::Value(Object::Return(object))) => break Ok(*object),
+                Ok(Tail::Value(object)) => break Ok(object),
+                Err(err) => break Err(err),
+            }
+        };
+        if result.is_err() && self.stack_trace.is_empty() {
+            self.stack_trace = self.call_stack.iter().rev().cloned().collect();
         }
-        self.env = Rc::new(RefCell::new(scoped_env));
-        let object = self.eval_stmts(body)?;
+        self.call_stack.truncate(depth);
         self.env = current_env;
-        Some(object)
+        result
+    }
+}
+
+pub(crate) fn eval_prefix_expr(prefix: Prefix, right: Object) -> Result<Object, RuntimeError> {
+    match prefix {
+        Prefix::Bang => match right {
+            Object::Bool(value) => Ok(Object::Bool(!value)),
+            Object::Null => Ok(Object::Bool(true)),
+            _ => Ok(Object::Bool(false)),
+        },
+        Prefix::Minus => match right {
+            Object::Int(value) => Ok(value
+                .checked_neg()
+                .map_or_else(|| Object::from(-BigInt::from(value)), Object::Int)),
+            Object::BigInt(value) => Ok(Object::from(-value)),
+            Object::Float(value) => Ok(Object::Float(-value)),
+            object => Err(RuntimeError::UnknownPrefixOperator {
+                prefix,
+                right: object.get_type(),
+                span: Span::default(),
+            }),
+        },
+    }
+}
+
+pub(crate) fn eval_infix_expr(
+    infix: Infix,
+    left: Object,
+    right: Object,
+) -> Result<Object, RuntimeError> {
+    match infix {
+        Infix::And if is_truthy(left.clone()) => return Ok(right),
+        Infix::Or if !is_truthy(left.clone()) => return Ok(right),
+        Infix::And | Infix::Or => return Ok(left),
+        _ => {}
+    }
+    match (left, right) {
+        (Object::Int(left), Object::Int(right)) => {
+            let value = match infix {
+                Infix::Plus => left.checked_add(right),
+                Infix::Minus => left.checked_sub(right),
+                Infix::Asterisk => left.checked_mul(right),
+                Infix::Slash if right != 0 => left.checked_div(right),
+                Infix::Percent if right != 0 => left.checked_rem(right),
+                Infix::Power => u32::try_from(right)
+                    .ok()
+                    .and_then(|exp| left.checked_pow(exp)),
+                Infix::Lt => return Ok(Object::Bool(left < right)),
+                Infix::Gt => return Ok(Object::Bool(left > right)),
+                Infix::Le => return Ok(Object::Bool(left <= right)),
+                Infix::Ge => return Ok(Object::Bool(left >= right)),
+                Infix::Eq => return Ok(Object::Bool(left == right)),
+                Infix::Ne => return Ok(Object::Bool(left != right)),
+                _ => None,
+            };
+            match value {
+                Some(value) => Ok(Object::Int(value)),
+                None => eval_bigint_infix_expr(infix, BigInt::from(left), BigInt::from(right)),
+            }
+        }
+        (
+            left @ (Object::Int(_) | Object::BigInt(_)),
+            right @ (Object::Int(_) | Object::BigInt(_)),
+        ) => eval_bigint_infix_expr(infix, into_bigint(left), into_bigint(right)),
+        (Object::Float(left), Object::Float(right)) => eval_float_infix_expr(infix, left, right),
+        (Object::Float(left), right @ (Object::Int(_) | Object::BigInt(_))) => {
+            eval_float_infix_expr(infix, left, into_f64(right))
+        }
+        (left @ (Object::Int(_) | Object::BigInt(_)), Object::Float(right)) => {
+            eval_float_infix_expr(infix, into_f64(left), right)
+        }
+        (Object::String(left), Object::String(right)) => match infix {
+            Infix::Plus => Ok(Object::String(format!("{}{}", left, right))),
+            Infix::Eq => Ok(Object::Bool(left == right)),
+            Infix::Ne => Ok(Object::Bool(left != right)),
+            infix => Err(RuntimeError::UnknownInfixOperator {
+                left: Object::String(left).get_type(),
+                infix,
+                right: Object::String(right).get_type(),
+                span: Span::default(),
+            }),
+        },
+        (Object::Bool(left), Object::Bool(right)) => match infix {
+            Infix::Eq => Ok(Object::Bool(left == right)),
+            Infix::Ne => Ok(Object::Bool(left != right)),
+            infix => Err(RuntimeError::UnknownInfixOperator {
+                left: Object::Bool(left).get_type(),
+                infix,
+                right: Object::Bool(right).get_type(),
+                span: Span::default(),
+            }),
+        },
+        (left, right) => Err(RuntimeError::TypeMismatch {
+            left: left.get_type(),
+            infix,
+            right: right.get_type(),
+            span: Span::default(),
+        }),
+    }
+}
+
+fn eval_bigint_infix_expr(
+    infix: Infix,
+    left: BigInt,
+    right: BigInt,
+) -> Result<Object, RuntimeError> {
+    match infix {
+        Infix::Plus => Ok(Object::from(left + right)),
+        Infix::Minus => Ok(Object::from(left - right)),
+        Infix::Asterisk => Ok(Object::from(left * right)),
+        Infix::Slash | Infix::Percent if right.is_zero() => Err(RuntimeError::DivisionByZero {
+            span: Span::default(),
+        }),
+        Infix::Slash => Ok(Object::from(left / right)),
+        Infix::Percent => Ok(Object::from(left % right)),
+        Infix::Power if right.is_negative() => Err(RuntimeError::NegativeExponent {
+            span: Span::default(),
+        }),
+        Infix::Power => match right.to_u32() {
+            Some(exp) => Ok(Object::from(left.pow(exp))),
+            None if left.magnitude() <= &BigUint::one() => {
+                let exp: u32 = if (right % 2u32).is_zero() { 2 } else { 1 };
+                Ok(Object::from(left.pow(exp)))
+            }
+            None => Err(RuntimeError::IntegerOverflow {
+                span: Span::default(),
+            }),
+        },
+        Infix::Lt => Ok(Object::Bool(left < right)),
+        Infix::Gt => Ok(Object::Bool(left > right)),
+        Infix::Le => Ok(Object::Bool(left <= right)),
+        Infix::Ge => Ok(Object::Bool(left >= right)),
+        Infix::Eq => Ok(Object::Bool(left == right)),
+        Infix::Ne => Ok(Object::Bool(left != right)),
+        Infix::And | Infix::Or => unreachable!(),
+    }
+}
+
+fn into_bigint(object: Object) -> BigInt {
+    match object {
+        Object::Int(value) => BigInt::from(value),
+        Object::BigInt(value) => value,
+        _ => unreachable!(),
+    }
+}
+
+fn eval_float_infix_expr(infix: Infix, left: f64, right: f64) -> Result<Object, RuntimeError> {
+    match infix {
+        Infix::Plus => Ok(Object::Float(left + right)),
+        Infix::Minus => Ok(Object::Float(left - right)),
+        Infix::Asterisk => Ok(Object::Float(left * right)),
+        Infix::Slash | Infix::Percent if right == 0.0 => Err(RuntimeError::DivisionByZero {
+            span: Span::default(),
+        }),
+        Infix::Slash => Ok(Object::Float(left / right)),
+        Infix::Percent => Ok(Object::Float(left % right)),
+        Infix::Power => Ok(Object::Float(left.powf(right))),
+        Infix::Lt => Ok(Object::Bool(left < right)),
+        Infix::Gt => Ok(Object::Bool(left > right)),
+        Infix::Le => Ok(Object::Bool(left <= right)),
+        Infix::Ge => Ok(Object::Bool(left >= right)),
+        Infix::Eq => Ok(Object::Bool(left == right)),
+        Infix::Ne => Ok(Object::Bool(left != right)),
+        Infix::And | Infix::Or => unreachable!(),
+    }
+}
+
+fn into_f64(object: Object) -> f64 {
+    match object {
+        Object::Int(value) => value as f64,
+        Object::BigInt(value) => value.to_f64().unwrap_or(f64::NAN),
+        _ => unreachable!(),
     }
 }
 
-fn is_truthy(object: Object) -> bool {
+pub(crate) fn eval_index_expr(left: Object, index: Object) -> Result<Object, RuntimeError> {
+    match (left, index) {
+        (Object::Array(elements), Object::Int(index)) => {
+            if index < 0 {
+                return Ok(Object::Null);
+            }
+            Ok(elements
+                .get(index as usize)
+                .cloned()
+                .unwrap_or(Object::Null))
+        }
+        (Object::Array(_), Object::BigInt(_)) => Ok(Object::Null),
+        (Object::Hash(pairs), index) => {
+            let key = HashKey::try_from(&index)?;
+            Ok(pairs.get(&key).cloned().unwrap_or(Object::Null))
+        }
+        (left, _) => Err(RuntimeError::IndexNotSupported {
+            left: left.get_type(),
+            span: Span::default(),
+        }),
+    }
+}
+
+pub(crate) fn iterate(object: Object) -> Result<Vec<Object>, RuntimeError> {
+    match object {
+        Object::Array(elements) => Ok(elements),
+        Object::String(value) => Ok(value
+            .chars()
+            .map(|ch| Object::String(ch.to_string()))
+            .collect()),
+        Object::Hash(pairs) => Ok(pairs.into_keys().map(Object::from).collect()),
+        object => Err(RuntimeError::NotIterable {
+            iterable: object.get_type(),
+            span: Span::default(),
+        }),
+    }
+}
+
+pub(crate) fn is_truthy(object: Object) -> bool {
     !matches!(object, Object::Bool(false) | Object::Null)
 }
 
-fn is_error(object: &Object) -> bool {
-    matches!(object, Object::Error(_))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn eval(input: &str) -> Result<Object, RuntimeError> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+        let env = Rc::new(RefCell::new(Environment::new()));
+        Evaluator::new(env).eval(program)
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        for input in [
+            "let f = fn(n) { if (n > 0) { return f(n - 1); } 0 }; f(1000000)",
+            "let f = fn(n) { while (true) { if (n == 0) { return 0; } return f(n - 1); } }; f(100000)",
+            "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }; f(100000)",
+            "let even = fn(n) { if (n == 0) { return 0; } odd(n - 1) }; let odd = fn(n) { even(n) }; even(100000)",
+        ] {
+            assert_eq!(eval(input), Ok(Object::Int(0)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let err = eval("let f = fn(n) { f(n + 1) + 1 }; f(0)").unwrap_err();
+        assert_eq!(err.code(), "E1012");
+        let input = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(5)";
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let limited = Evaluator::new(env).with_max_depth(5).eval(program);
+        assert_eq!(limited.unwrap_err().code(), "E1012");
+        assert_eq!(eval(input), Ok(Object::Int(5)));
+        let nested = format!("{}1{}", "-(".repeat(4000), ")".repeat(4000));
+        assert_eq!(eval(&nested), Ok(Object::Int(1)));
+    }
 }
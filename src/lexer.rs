@@ -1,70 +1,174 @@
-use crate::token::Token;
+use crate::{
+    diagnostics::Diagnostic,
+    token::{Span, Token},
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Template {
+        start: usize,
+        line: usize,
+        col: usize,
+    },
+    Interpolation(usize),
+}
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
     input: &'a str,
     position: usize,
     read_position: usize,
-    ch: u8,
+    ch: char,
+    line: usize,
+    col: usize,
+    trivia: bool,
+    modes: Vec<Mode>,
+    errors: Vec<Diagnostic>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Lexer::new_at(input, 0)
+    }
+
+    pub fn new_at(input: &'a str, position: usize) -> Self {
+        let line_start = input[..position].rfind('\n').map_or(0, |i| i + 1);
         let mut lexer = Lexer {
             input,
-            position: 0,
-            read_position: 0,
-            ch: 0,
+            position,
+            read_position: position,
+            ch: '\0',
+            line: input[..position].matches('\n').count() + 1,
+            col: input[line_start..position].chars().count(),
+            trivia: false,
+            modes: Vec::new(),
+            errors: Vec::new(),
         };
         lexer.read_char();
         lexer
     }
 
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0;
-        } else {
-            self.ch = self.input.as_bytes()[self.read_position];
+        if self.ch == '\n' {
+            self.line += 1;
+            self.col = 0;
         }
-
+        self.col += 1;
+        self.ch = self.peek_char();
         self.position = self.read_position;
-        self.read_position += 1;
+        self.read_position += self.ch.len_utf8();
     }
 
-    pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+    pub fn next_token(&mut self) -> (Token, Span) {
+        loop {
+            let template = matches!(self.modes.last(), Some(Mode::Template { .. }));
+            if !template {
+                self.skip_whitespace();
+            }
+
+            let start = self.position.min(self.input.len());
+            let (line, col) = (self.line, self.col);
+            let token = if template {
+                self.read_template()
+            } else {
+                self.read_token()
+            };
+            let span = Span {
+                start,
+                end: self.position.min(self.input.len()),
+                line,
+                col,
+            };
+            if self.trivia || !matches!(token, Token::COMMENT(_)) {
+                return (token, span);
+            }
+        }
+    }
 
+    fn read_token(&mut self) -> Token {
         let token = match self.ch {
-            b'=' => {
-                if self.peek_char() == b'=' {
+            '=' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     Token::EQ
                 } else {
                     Token::ASSIGN
                 }
             }
-            b';' => Token::SEMICOLON,
-            b'(' => Token::LPAREN,
-            b')' => Token::RPAREN,
-            b',' => Token::COMMA,
-            b'+' => Token::PLUS,
-            b'-' => Token::MINUS,
-            b'!' => {
-                if self.peek_char() == b'=' {
+            ';' => Token::SEMICOLON,
+            ':' => Token::COLON,
+            '(' => Token::LPAREN,
+            ')' => Token::RPAREN,
+            ',' => Token::COMMA,
+            '+' => self.read_compound(Token::PLUS, Token::PLUSASSIGN),
+            '-' => self.read_compound(Token::MINUS, Token::MINUSASSIGN),
+            '!' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     Token::NE
                 } else {
                     Token::BANG
                 }
             }
-            b'*' => Token::ASTERISK,
-            b'/' => Token::SLASH,
-            b'<' => Token::LT,
-            b'>' => Token::GT,
-            b'{' => Token::LBRACE,
-            b'}' => Token::RBRACE,
-            b'"' => self.read_string(),
-            0 => Token::EOF,
+            '*' if self.peek_char() == '*' => {
+                self.read_char();
+                Token::POWER
+            }
+            '*' => self.read_compound(Token::ASTERISK, Token::ASTERISKASSIGN),
+            '%' => Token::PERCENT,
+            '/' if self.peek_char() == '/' => return self.read_line_comment(),
+            '/' if self.peek_char() == '*' => return self.read_block_comment(),
+            '/' => self.read_compound(Token::SLASH, Token::SLASHASSIGN),
+            '&' if self.peek_char() == '&' => {
+                self.read_char();
+                Token::AND
+            }
+            '|' if self.peek_char() == '|' => {
+                self.read_char();
+                Token::OR
+            }
+            '<' => self.read_compound(Token::LT, Token::LE),
+            '>' => self.read_compound(Token::GT, Token::GE),
+            '{' => {
+                if let Some(Mode::Interpolation(depth)) = self.modes.last_mut() {
+                    *depth += 1;
+                }
+                Token::LBRACE
+            }
+            '}' => {
+                match self.modes.last_mut() {
+                    Some(Mode::Interpolation(0)) => {
+                        self.modes.pop();
+                    }
+                    Some(Mode::Interpolation(depth)) => *depth -= 1,
+                    _ => {}
+                }
+                Token::RBRACE
+            }
+            '[' => Token::LBRACKET,
+            ']' => Token::RBRACKET,
+            '"' => self.read_string(),
+            '`' => {
+                self.modes.push(Mode::Template {
+                    start: self.position,
+                    line: self.line,
+                    col: self.col,
+                });
+                Token::TEMPLATESTART
+            }
+            '\0' => {
+                self.close_templates();
+                Token::EOF
+            }
             _ => {
                 if is_letter(self.ch) {
                     return self.read_identifier();
@@ -80,6 +184,15 @@ impl<'a> Lexer<'a> {
         token
     }
 
+    fn read_compound(&mut self, token: Token, assign: Token) -> Token {
+        if self.peek_char() == '=' {
+            self.read_char();
+            assign
+        } else {
+            token
+        }
+    }
+
     fn read_identifier(&mut self) -> Token {
         let position = self.position;
         while is_letter(self.ch) {
@@ -88,53 +201,338 @@ impl<'a> Lexer<'a> {
         match &self.input[position..self.position] {
             "fn" => Token::FUNCTION,
             "let" => Token::LET,
+            "const" => Token::CONST,
             "true" => Token::BOOL(true),
             "false" => Token::BOOL(false),
             "if" => Token::IF,
             "else" => Token::ELSE,
             "return" => Token::RETURN,
+            "while" => Token::WHILE,
+            "for" => Token::FOR,
+            "in" => Token::IN,
+            "break" => Token::BREAK,
+            "continue" => Token::CONTINUE,
             ident => Token::IDENT(ident.to_string()),
         }
     }
 
     fn read_number(&mut self) -> Token {
         let position = self.position;
+        self.read_digits();
+        let mut float = false;
+        if self.ch == '.' && is_digit(self.peek_char()) {
+            float = true;
+            self.read_char();
+            self.read_digits();
+        }
+        if matches!(self.ch, 'e' | 'E') {
+            let sign = matches!(self.peek_char(), '+' | '-') as usize;
+            if is_digit(self.peek_nth(sign)) {
+                float = true;
+                for _ in 0..=sign {
+                    self.read_char();
+                }
+                self.read_digits();
+            }
+        }
+        let literal = &self.input[position..self.position];
+        if float {
+            return Token::FLOAT(literal.parse().unwrap());
+        }
+        match literal.parse::<i64>() {
+            Ok(value) => Token::INT(value),
+            Err(_) => Token::BIGINT(literal.parse().unwrap()),
+        }
+    }
+
+    fn read_line_comment(&mut self) -> Token {
+        let position = self.position;
+        while !matches!(self.ch, '\n' | '\0') {
+            self.read_char();
+        }
+        let comment = &self.input[position..self.position];
+        match comment.strip_prefix("///") {
+            Some(doc) if !doc.starts_with('/') => Token::DOC(doc.to_string()),
+            _ => Token::COMMENT(comment.to_string()),
+        }
+    }
+
+    fn read_block_comment(&mut self) -> Token {
+        let (start, line, col) = (self.position, self.line, self.col);
+        let mut depth = 0;
+        loop {
+            match (self.ch, self.peek_char()) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.read_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.read_char();
+                }
+                ('\0', _) => {
+                    self.unterminated("block comment", start, line, col);
+                    break;
+                }
+                _ => {}
+            }
+            self.read_char();
+            if depth == 0 {
+                break;
+            }
+        }
+        Token::COMMENT(self.input[start..self.position.min(self.input.len())].to_string())
+    }
+
+    fn read_digits(&mut self) {
         while is_digit(self.ch) {
             self.read_char();
         }
-        Token::INT(self.input[position..self.position].parse::<i64>().unwrap())
     }
 
     fn read_string(&mut self) -> Token {
-        let position = self.position + 1;
+        let (start, line, col) = (self.position, self.line, self.col);
+        let mut value = String::new();
         loop {
             self.read_char();
-            if self.ch == b'"' || self.ch == 0 {
-                break;
+            match self.ch {
+                '"' => break,
+                '\0' => {
+                    self.unterminated("string literal", start, line, col);
+                    break;
+                }
+                '\\' => {
+                    if let Some(ch) = self.read_escape() {
+                        value.push(ch);
+                    }
+                }
+                ch => value.push(ch),
             }
         }
-        Token::STRING(self.input[position..self.position].to_string())
+        Token::STRING(value)
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.ch.is_ascii_whitespace() {
+    fn read_template(&mut self) -> Token {
+        let mut literal = String::new();
+        loop {
+            match self.ch {
+                '`' | '\0' => break,
+                '$' if self.peek_char() == '{' => break,
+                '\\' => {
+                    if let Some(ch) = self.read_escape() {
+                        literal.push(ch);
+                    }
+                }
+                ch => literal.push(ch),
+            }
             self.read_char();
         }
+        if !literal.is_empty() {
+            return Token::TEMPLATE(literal);
+        }
+        match self.ch {
+            '`' => {
+                self.modes.pop();
+                self.read_char();
+                Token::TEMPLATEEND
+            }
+            '\0' => {
+                self.close_templates();
+                Token::EOF
+            }
+            _ => {
+                self.read_char();
+                self.read_char();
+                self.modes.push(Mode::Interpolation(0));
+                Token::INTERPOLATION
+            }
+        }
     }
 
-    fn peek_char(&mut self) -> u8 {
-        if self.read_position >= self.input.len() {
-            0
-        } else {
-            self.input.as_bytes()[self.read_position]
+    fn close_templates(&mut self) {
+        let template = self.modes.iter().rev().find_map(|mode| match *mode {
+            Mode::Template { start, line, col } => Some((start, line, col)),
+            Mode::Interpolation(_) => None,
+        });
+        if let Some((start, line, col)) = template {
+            self.unterminated("template string", start, line, col);
+        }
+        self.modes.clear();
+    }
+
+    fn unterminated(&mut self, what: &str, start: usize, line: usize, col: usize) {
+        let span = Span {
+            start,
+            end: self.input.len(),
+            line,
+            col,
+        };
+        self.errors
+            .push(Diagnostic::error(format!("unterminated {}", what), span).with_code("E0005"));
+    }
+
+    fn read_escape(&mut self) -> Option<char> {
+        let (start, line, col) = (self.position, self.line, self.col);
+        let ch = match self.peek_char() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '`' => Some('`'),
+            '$' => Some('$'),
+            'u' => {
+                self.read_char();
+                return self.read_unicode_escape(start, line, col);
+            }
+            '\0' => return None,
+            _ => None,
+        };
+        self.read_char();
+        if ch.is_none() {
+            let span = Span {
+                start,
+                end: self.read_position,
+                line,
+                col,
+            };
+            self.errors.push(
+                Diagnostic::error(
+                    format!(
+                        "unknown character escape `{}`",
+                        &self.input[start..span.end]
+                    ),
+                    span,
+                )
+                .with_code("E0006"),
+            );
+        }
+        ch
+    }
+
+    fn read_unicode_escape(&mut self, start: usize, line: usize, col: usize) -> Option<char> {
+        let mut ch = None;
+        if self.peek_char() == '{' {
+            self.read_char();
+            let position = self.read_position;
+            while self.peek_char().is_ascii_hexdigit() {
+                self.read_char();
+            }
+            let digits = &self.input[position..self.read_position];
+            if self.peek_char() == '}' {
+                self.read_char();
+                ch = u32::from_str_radix(digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() <= 6)
+                    .and_then(char::from_u32);
+            }
+        }
+        if ch.is_none() {
+            let span = Span {
+                start,
+                end: self.read_position,
+                line,
+                col,
+            };
+            self.errors.push(
+                Diagnostic::error(
+                    format!("invalid unicode escape `{}`", &self.input[start..span.end]),
+                    span,
+                )
+                .with_code("E0006")
+                .with_note("unicode escapes must look like `\\u{XXXX}` with at most 6 hex digits"),
+            );
+        }
+        ch
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.ch.is_whitespace() {
+            self.read_char();
         }
     }
+
+    fn peek_char(&self) -> char {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> char {
+        self.input
+            .get(self.read_position..)
+            .and_then(|rest| rest.chars().nth(n))
+            .unwrap_or('\0')
+    }
 }
 
-fn is_letter(ch: u8) -> bool {
-    ch.is_ascii_lowercase() || ch.is_ascii_uppercase() || ch == b'_'
+fn is_letter(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
 }
 
-fn is_digit(ch: u8) -> bool {
+fn is_digit(ch: char) -> bool {
     ch.is_ascii_digit()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(mut lexer: Lexer) -> (Vec<Token>, Vec<String>) {
+        let mut tokens = Vec::new();
+        loop {
+            match lexer.next_token() {
+                (Token::EOF, _) => break,
+                (token, _) => tokens.push(token),
+            }
+        }
+        let codes = lexer.errors().iter().filter_map(|err| err.code.clone());
+        (tokens, codes.collect())
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let (tokens, errors) = lex(Lexer::new(r#""say \"hi\"\n\t\\ \u{1F600}" café"#));
+        assert_eq!(
+            tokens,
+            vec![
+                Token::STRING("say \"hi\"\n\t\\ \u{1F600}".to_string()),
+                Token::IDENT("café".to_string()),
+            ]
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        for input in [r#""\q""#, r#""\u{110000}""#, r#""\u{41""#, r#""\u41""#] {
+            assert_eq!(lex(Lexer::new(input)).1, vec!["E0006"], "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let (tokens, errors) = lex(Lexer::new("\"abc"));
+        assert_eq!(tokens, vec![Token::STRING("abc".to_string())]);
+        assert_eq!(errors, vec!["E0005"]);
+        let (_, errors) = lex(Lexer::new("\"abc\\"));
+        assert_eq!(errors, vec!["E0005"]);
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "1 // line\n/* a /* nested */ b */ / 2 /**/";
+        let (tokens, errors) = lex(Lexer::new(input));
+        assert_eq!(tokens, vec![Token::INT(1), Token::SLASH, Token::INT(2)]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (tokens, _) = lex(Lexer::new(input).with_trivia());
+        assert_eq!(
+            tokens,
+            vec![
+                Token::INT(1),
+                Token::COMMENT("// line".to_string()),
+                Token::COMMENT("/* a /* nested */ b */".to_string()),
+                Token::SLASH,
+                Token::INT(2),
+                Token::COMMENT("/**/".to_string()),
+            ]
+        );
+        let (tokens, errors) = lex(Lexer::new("1 /* a /* b */ 2"));
+        assert_eq!(tokens, vec![Token::INT(1)]);
+        assert_eq!(errors, vec!["E0005"]);
+    }
+}
@@ -1,7 +1,17 @@
 pub mod ast;
+pub mod builtins;
+pub mod code;
+pub mod compiler;
+pub mod diagnostics;
 pub mod environment;
+pub mod error;
 pub mod evaluator;
 pub mod lexer;
 pub mod object;
 pub mod parser;
+pub mod symbol_table;
 pub mod token;
+pub mod vm;
+
+const RED_ZONE: usize = 64 * 1024;
+const STACK_SEGMENT: usize = 1024 * 1024;
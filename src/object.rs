@@ -1,30 +1,110 @@
 use crate::{
     ast::{BlockStmt, Ident},
+    code::Instructions,
     environment::Environment,
+    error::RuntimeError,
+    token::Span,
 };
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display, rc::Rc};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     Int(i64),
+    BigInt(BigInt),
+    Float(f64),
     Bool(bool),
     String(String),
     Null,
     Return(Box<Object>),
-    Error(String),
-    Function(Vec<Ident>, BlockStmt, Rc<RefCell<Environment>>),
+    Break,
+    Continue,
+    Function(Rc<Function>),
+    Builtin(Builtin),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
+    CompiledFunction(Rc<CompiledFunction>),
+    Closure(Rc<Closure>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub params: Vec<Ident>,
+    pub body: BlockStmt,
+    pub env: Rc<RefCell<Environment>>,
+    pub span: Span,
+    pub name: Option<String>,
+    pub doc: Option<String>,
+}
+
+pub type BuiltinFunction = fn(Vec<Object>) -> Result<Object, RuntimeError>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub func: BuiltinFunction,
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Builtin {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledFunction {
+    pub instructions: Instructions,
+    pub num_locals: usize,
+    pub num_params: usize,
+    pub doc: Option<String>,
+    pub span: Span,
+    pub spans: Vec<(usize, Span)>,
+    pub errors: Vec<RuntimeError>,
+}
+
+impl CompiledFunction {
+    pub fn span_at(&self, position: usize) -> Span {
+        match self.spans.partition_point(|(start, _)| *start <= position) {
+            0 => Span::default(),
+            i => self.spans[i - 1].1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Closure {
+    pub func: Rc<CompiledFunction>,
+    pub free: Vec<Rc<RefCell<Object>>>,
 }
 
 impl Object {
     pub fn get_type(&self) -> String {
         match self {
-            Object::Int(_) => "INT".to_string(),
+            Object::Int(_) | Object::BigInt(_) => "INT".to_string(),
+            Object::Float(_) => "FLOAT".to_string(),
             Object::Bool(_) => "BOOL".to_string(),
             Object::String(_) => "STRING".to_string(),
             Object::Null => "NULL".to_string(),
             Object::Return(_) => "RETURN".to_string(),
-            Object::Error(_) => "ERROR".to_string(),
-            Object::Function(_, _, _) => "FUNCTION".to_string(),
+            Object::Break => "BREAK".to_string(),
+            Object::Continue => "CONTINUE".to_string(),
+            Object::Function(_) => "FUNCTION".to_string(),
+            Object::Builtin(_) => "BUILTIN".to_string(),
+            Object::Array(_) => "ARRAY".to_string(),
+            Object::Hash(_) => "HASH".to_string(),
+            Object::CompiledFunction(_) => "FUNCTION".to_string(),
+            Object::Closure(_) => "FUNCTION".to_string(),
+        }
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Object::Function(func) => func.doc.as_deref(),
+            Object::Closure(closure) => closure.func.doc.as_deref(),
+            _ => None,
         }
     }
 }
@@ -33,12 +113,92 @@ impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Object::Int(value) => write!(f, "{}", value),
+            Object::BigInt(value) => write!(f, "{}", value),
+            Object::Float(value) => write!(f, "{:?}", value),
             Object::Bool(value) => write!(f, "{}", value),
             Object::String(value) => write!(f, "{}", value),
             Object::Null => write!(f, "null"),
             Object::Return(value) => write!(f, "{}", value),
-            Object::Error(value) => write!(f, "{}", value),
-            Object::Function(_, _, _) => write!(f, "function"),
+            Object::Break => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
+            Object::Function(_) => write!(f, "function"),
+            Object::Builtin(builtin) => write!(f, "builtin function {}", builtin.name),
+            Object::Array(elements) => write!(
+                f,
+                "[{}]",
+                elements
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Object::CompiledFunction(_) => write!(f, "function"),
+            Object::Closure(_) => write!(f, "function"),
+            Object::Hash(pairs) => write!(
+                f,
+                "{{{}}}",
+                pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Int(i64),
+    BigInt(BigInt),
+    Bool(bool),
+    String(String),
+}
+
+impl TryFrom<&Object> for HashKey {
+    type Error = RuntimeError;
+
+    fn try_from(object: &Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Int(value) => Ok(HashKey::Int(*value)),
+            Object::BigInt(value) => Ok(HashKey::BigInt(value.clone())),
+            Object::Bool(value) => Ok(HashKey::Bool(*value)),
+            Object::String(value) => Ok(HashKey::String(value.clone())),
+            object => Err(RuntimeError::UnusableHashKey {
+                key: object.get_type(),
+                span: Span::default(),
+            }),
+        }
+    }
+}
+
+impl From<BigInt> for Object {
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Object::Int(value),
+            None => Object::BigInt(value),
+        }
+    }
+}
+
+impl From<HashKey> for Object {
+    fn from(key: HashKey) -> Self {
+        match key {
+            HashKey::Int(value) => Object::Int(value),
+            HashKey::BigInt(value) => Object::BigInt(value),
+            HashKey::Bool(value) => Object::Bool(value),
+            HashKey::String(value) => Object::String(value),
+        }
+    }
+}
+
+impl Display for HashKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashKey::Int(value) => write!(f, "{}", value),
+            HashKey::BigInt(value) => write!(f, "{}", value),
+            HashKey::Bool(value) => write!(f, "{}", value),
+            HashKey::String(value) => write!(f, "{}", value),
         }
     }
 }
@@ -1,14 +1,28 @@
 use crate::{
-    ast::{BlockStmt, Expr, Ident, Infix, Precedence, Prefix, Program, Stmt},
+    ast::{
+        BlockStmt, Expr, ExprKind, Ident, Infix, Precedence, Prefix, Program, Stmt, StmtKind,
+        TemplatePart,
+    },
+    diagnostics::Diagnostic,
     lexer::Lexer,
-    token::Token,
+    token::{Span, Token},
+    RED_ZONE, STACK_SEGMENT,
 };
 
+pub const MAX_NESTING_DEPTH: usize = 4096;
+
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     cur_token: Token,
+    cur_span: Span,
     peek_token: Token,
-    errors: Vec<String>,
+    peek_span: Span,
+    cur_doc: Option<String>,
+    peek_doc: Option<String>,
+    errors: Vec<Diagnostic>,
+    depth: usize,
+    loop_depth: usize,
+    template_depth: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -16,21 +30,50 @@ impl<'a> Parser<'a> {
         let mut parser = Parser {
             lexer,
             cur_token: Token::EOF,
+            cur_span: Span::default(),
             peek_token: Token::EOF,
+            peek_span: Span::default(),
+            cur_doc: None,
+            peek_doc: None,
             errors: Vec::new(),
+            depth: 0,
+            loop_depth: 0,
+            template_depth: 0,
         };
         parser.next_token();
         parser.next_token();
         parser
     }
 
-    pub fn errors(&self) -> Vec<String> {
-        self.errors.clone()
+    pub fn errors(&self) -> Vec<Diagnostic> {
+        let mut errors = self.lexer.errors().to_vec();
+        errors.extend(self.errors.iter().cloned());
+        errors.sort_by_key(|err| err.span.start);
+        errors
     }
 
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        self.cur_span = self.peek_span;
+        self.cur_doc = self.peek_doc.take();
+        match self.cur_token {
+            Token::TEMPLATESTART => self.template_depth += 1,
+            Token::TEMPLATEEND => self.template_depth -= 1,
+            _ => {}
+        }
+        let mut doc = Vec::new();
+        loop {
+            (self.peek_token, self.peek_span) = self.lexer.next_token();
+            match self.peek_token {
+                Token::DOC(ref line) => {
+                    doc.push(line.strip_prefix(' ').unwrap_or(line).to_string())
+                }
+                _ => break,
+            }
+        }
+        if !doc.is_empty() {
+            self.peek_doc = Some(doc.join("\n"));
+        }
     }
 
     fn is_cur_token(&self, token: &Token) -> bool {
@@ -52,17 +95,50 @@ impl<'a> Parser<'a> {
     }
 
     fn peek_error(&mut self, token: Token) {
-        self.errors.push(format!(
-            "expected next token to be {}, got {} instead",
-            token, self.peek_token
-        ));
+        self.errors.push(
+            Diagnostic::error(
+                format!(
+                    "expected next token to be {}, got {} instead",
+                    token, self.peek_token
+                ),
+                self.peek_span,
+            )
+            .with_code("E0001")
+            .with_label(self.cur_span, &format!("after this {}", self.cur_token)),
+        );
     }
 
     fn no_prefix_error(&mut self) {
-        self.errors.push(format!(
-            "no prefix parse function for {} found",
-            self.cur_token
-        ));
+        self.errors.push(
+            Diagnostic::error(
+                format!("no prefix parse function for {} found", self.cur_token),
+                self.cur_span,
+            )
+            .with_code("E0002"),
+        );
+    }
+
+    fn nest(&mut self) -> Option<()> {
+        self.depth += 1;
+        self.check_depth(self.depth)
+    }
+
+    fn check_depth(&mut self, depth: usize) -> Option<()> {
+        if depth <= MAX_NESTING_DEPTH {
+            return Some(());
+        }
+        self.errors.push(
+            Diagnostic::error("expression nested too deeply".to_string(), self.cur_span)
+                .with_code("E0003")
+                .with_note(&format!(
+                    "the nesting limit is {}, and each chained operator adds a level",
+                    MAX_NESTING_DEPTH
+                )),
+        );
+        while !self.is_peek_token(&Token::EOF) {
+            self.next_token();
+        }
+        None
     }
 
     pub fn parse_program(&mut self) -> Program {
@@ -78,20 +154,54 @@ impl<'a> Parser<'a> {
 
     fn parse_stmt(&mut self) -> Option<Stmt> {
         match self.cur_token {
-            Token::LET => self.parse_let_stmt(),
+            Token::LET | Token::CONST => self.parse_let_stmt(),
             Token::RETURN => self.parse_return_stmt(),
+            Token::WHILE => self.parse_while_stmt(),
+            Token::FOR => self.parse_for_stmt(),
+            Token::BREAK | Token::CONTINUE => self.parse_jump_stmt(),
+            Token::IDENT(_)
+                if matches!(
+                    self.peek_token,
+                    Token::ASSIGN
+                        | Token::PLUSASSIGN
+                        | Token::MINUSASSIGN
+                        | Token::ASTERISKASSIGN
+                        | Token::SLASHASSIGN
+                ) =>
+            {
+                self.parse_assign_stmt()
+            }
             _ => self.parse_expr_stmt(),
         }
     }
 
-    fn parse_let_stmt(&mut self) -> Option<Stmt> {
-        let ident = match self.peek_token {
+    fn expect_peek_ident(&mut self) -> Option<Ident> {
+        match self.peek_token {
             Token::IDENT(_) => {
                 self.next_token();
-                self.parse_ident()?
+                self.parse_ident()
             }
-            _ => return None,
-        };
+            _ => {
+                self.errors.push(
+                    Diagnostic::error(
+                        format!(
+                            "expected next token to be identifier, got {} instead",
+                            self.peek_token
+                        ),
+                        self.peek_span,
+                    )
+                    .with_code("E0001"),
+                );
+                None
+            }
+        }
+    }
+
+    fn parse_let_stmt(&mut self) -> Option<Stmt> {
+        let start = self.cur_span;
+        let constant = self.is_cur_token(&Token::CONST);
+        let doc = self.cur_doc.take();
+        let ident = self.expect_peek_ident()?;
         if !self.expect_peek(Token::ASSIGN) {
             return None;
         }
@@ -100,31 +210,173 @@ impl<'a> Parser<'a> {
             Some(expr) => expr,
             _ => return None,
         };
+        self.check_jumps(&expr);
+        let span = start.to(self.cur_span);
         while self.is_peek_token(&Token::SEMICOLON) {
             self.next_token();
         }
-        Some(Stmt::Let(ident, expr))
+        let kind = if constant {
+            StmtKind::Const(ident, expr, doc)
+        } else {
+            StmtKind::Let(ident, expr, doc)
+        };
+        Some(Stmt { kind, span })
+    }
+
+    fn parse_assign_stmt(&mut self) -> Option<Stmt> {
+        let start = self.cur_span;
+        let ident = self.parse_ident()?;
+        self.next_token();
+        let infix = match self.cur_token {
+            Token::PLUSASSIGN => Some(Infix::Plus),
+            Token::MINUSASSIGN => Some(Infix::Minus),
+            Token::ASTERISKASSIGN => Some(Infix::Asterisk),
+            Token::SLASHASSIGN => Some(Infix::Slash),
+            _ => None,
+        };
+        self.next_token();
+        let expr = self.parse_expr(Precedence::Lowest)?;
+        self.check_jumps(&expr);
+        let span = start.to(self.cur_span);
+        while self.is_peek_token(&Token::SEMICOLON) {
+            self.next_token();
+        }
+        Some(Stmt {
+            kind: StmtKind::Assign(ident, infix, expr),
+            span,
+        })
     }
 
     fn parse_return_stmt(&mut self) -> Option<Stmt> {
+        let start = self.cur_span;
         self.next_token();
         let expr = match self.parse_expr(Precedence::Lowest) {
             Some(expr) => expr,
             _ => return None,
         };
+        self.check_jumps(&expr);
+        let span = start.to(self.cur_span);
         while self.is_peek_token(&Token::SEMICOLON) {
             self.next_token();
         }
-        Some(Stmt::Return(expr))
+        Some(Stmt {
+            kind: StmtKind::Return(expr),
+            span,
+        })
+    }
+
+    fn parse_while_stmt(&mut self) -> Option<Stmt> {
+        let start = self.cur_span;
+        if !self.expect_peek(Token::LPAREN) {
+            return None;
+        }
+        self.next_token();
+        let cond = self.parse_expr(Precedence::Lowest)?;
+        self.check_jumps(&cond);
+        if !self.expect_peek(Token::RPAREN) {
+            return None;
+        }
+        let body = self.parse_loop_body()?;
+        Some(Stmt {
+            kind: StmtKind::While(cond, body),
+            span: start.to(self.cur_span),
+        })
+    }
+
+    fn parse_for_stmt(&mut self) -> Option<Stmt> {
+        let start = self.cur_span;
+        if !self.expect_peek(Token::LPAREN) {
+            return None;
+        }
+        let ident = self.expect_peek_ident()?;
+        if !self.expect_peek(Token::IN) {
+            return None;
+        }
+        self.next_token();
+        let iterable = self.parse_expr(Precedence::Lowest)?;
+        self.check_jumps(&iterable);
+        if !self.expect_peek(Token::RPAREN) {
+            return None;
+        }
+        let body = self.parse_loop_body()?;
+        Some(Stmt {
+            kind: StmtKind::For(ident, iterable, body),
+            span: start.to(self.cur_span),
+        })
+    }
+
+    fn parse_loop_body(&mut self) -> Option<BlockStmt> {
+        if !self.expect_peek(Token::LBRACE) {
+            return None;
+        }
+        self.loop_depth += 1;
+        let body = self.parse_block_stmt();
+        self.loop_depth -= 1;
+        if !self.is_cur_token(&Token::RBRACE) {
+            return None;
+        }
+        while self.is_peek_token(&Token::SEMICOLON) {
+            self.next_token();
+        }
+        Some(body)
+    }
+
+    fn parse_jump_stmt(&mut self) -> Option<Stmt> {
+        let (kind, span) = match self.cur_token {
+            Token::BREAK => (StmtKind::Break, self.cur_span),
+            _ => (StmtKind::Continue, self.cur_span),
+        };
+        if self.loop_depth == 0 {
+            self.errors.push(
+                Diagnostic::error(format!("`{}` outside of a loop", self.cur_token), span)
+                    .with_code("E0004"),
+            );
+        }
+        while self.is_peek_token(&Token::SEMICOLON) {
+            self.next_token();
+        }
+        Some(Stmt { kind, span })
+    }
+
+    fn check_jumps(&mut self, expr: &Expr) {
+        if self.loop_depth == 0 {
+            return;
+        }
+        let Some(stmt) = find_jump(expr) else {
+            return;
+        };
+        let keyword = match stmt.kind {
+            StmtKind::Break => Token::BREAK,
+            _ => Token::CONTINUE,
+        };
+        self.errors.push(
+            Diagnostic::error(
+                format!("`{}` cannot be used inside an expression", keyword),
+                stmt.span,
+            )
+            .with_code("E0007")
+            .with_label(expr.span, "this expression must produce a value")
+            .with_note(&format!(
+                "`{}` is only allowed in an `if` used as a statement",
+                keyword
+            )),
+        );
     }
 
     fn parse_expr_stmt(&mut self) -> Option<Stmt> {
         match self.parse_expr(Precedence::Lowest) {
             Some(expr) => {
+                match &expr.kind {
+                    ExprKind::If(cond, ..) => self.check_jumps(cond),
+                    _ => self.check_jumps(&expr),
+                }
                 while self.is_peek_token(&Token::SEMICOLON) {
                     self.next_token();
                 }
-                Some(Stmt::Expr(expr))
+                Some(Stmt {
+                    span: expr.span,
+                    kind: StmtKind::Expr(expr),
+                })
             }
             _ => None,
         }
@@ -143,30 +395,51 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expr(&mut self, precedence: Precedence) -> Option<Expr> {
+        let depth = self.depth;
+        let expr = stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || self.parse_expr_at(precedence));
+        self.depth = depth;
+        expr
+    }
+
+    fn parse_expr_at(&mut self, precedence: Precedence) -> Option<Expr> {
+        self.nest()?;
         let mut left = match self.cur_token {
             Token::IDENT(_) => self.parse_ident_expr()?,
-            Token::INT(_) => self.parse_int_expr()?,
+            Token::INT(_) | Token::BIGINT(_) => self.parse_int_expr()?,
+            Token::FLOAT(_) => self.parse_float_expr()?,
             Token::BOOL(_) => self.parse_bool_expr()?,
             Token::STRING(_) => self.parse_string_expr()?,
+            Token::TEMPLATESTART => self.parse_template_expr()?,
             Token::MINUS | Token::BANG => self.parse_prefix_expr()?,
             Token::LPAREN => self.parse_grouped_expr()?,
             Token::IF => self.parse_if_expr()?,
             Token::FUNCTION => self.parse_function_expr()?,
+            Token::LBRACKET => self.parse_array_expr()?,
+            Token::LBRACE => self.parse_hash_expr()?,
             _ => {
                 self.no_prefix_error();
                 return None;
             }
         };
+        let mut height = 0;
         while !self.is_peek_token(&Token::SEMICOLON) && precedence < (&self.peek_token).into() {
+            height += 1;
+            self.check_depth(self.depth + height)?;
             match self.peek_token {
                 Token::PLUS
                 | Token::MINUS
                 | Token::ASTERISK
                 | Token::SLASH
+                | Token::PERCENT
+                | Token::POWER
                 | Token::LT
                 | Token::GT
+                | Token::LE
+                | Token::GE
                 | Token::EQ
-                | Token::NE => {
+                | Token::NE
+                | Token::AND
+                | Token::OR => {
                     self.next_token();
                     left = self.parse_infix_expr(left)?;
                 }
@@ -174,6 +447,10 @@ impl<'a> Parser<'a> {
                     self.next_token();
                     left = self.parse_call_expr(left)?;
                 }
+                Token::LBRACKET => {
+                    self.next_token();
+                    left = self.parse_index_expr(left)?;
+                }
                 _ => break,
             };
         }
@@ -181,14 +458,18 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_prefix_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
         let prefix = match self.cur_token {
             Token::MINUS => Prefix::Minus,
             Token::BANG => Prefix::Bang,
             _ => unreachable!(),
         };
         self.next_token();
-        self.parse_expr(Precedence::Prefix)
-            .map(|expr| Expr::Prefix(prefix, Box::new(expr)))
+        let right = self.parse_expr(Precedence::Prefix)?;
+        Some(Expr {
+            span: start.to(right.span),
+            kind: ExprKind::Prefix(prefix, Box::new(right)),
+        })
     }
 
     fn parse_infix_expr(&mut self, left: Expr) -> Option<Expr> {
@@ -197,28 +478,48 @@ impl<'a> Parser<'a> {
             Token::MINUS => Infix::Minus,
             Token::ASTERISK => Infix::Asterisk,
             Token::SLASH => Infix::Slash,
+            Token::PERCENT => Infix::Percent,
+            Token::POWER => Infix::Power,
             Token::LT => Infix::Lt,
             Token::GT => Infix::Gt,
+            Token::LE => Infix::Le,
+            Token::GE => Infix::Ge,
             Token::EQ => Infix::Eq,
             Token::NE => Infix::Ne,
+            Token::AND => Infix::And,
+            Token::OR => Infix::Or,
             _ => unreachable!(),
         };
-        let precedence = (&self.cur_token).into();
+        let precedence = match infix {
+            Infix::Power => Precedence::Prefix,
+            _ => (&self.cur_token).into(),
+        };
         self.next_token();
-        self.parse_expr(precedence)
-            .map(|right| Expr::Infix(infix, Box::new(left), Box::new(right)))
+        let right = self.parse_expr(precedence)?;
+        Some(Expr {
+            span: left.span.to(right.span),
+            kind: ExprKind::Infix(infix, Box::new(left), Box::new(right)),
+        })
     }
 
     fn parse_grouped_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
         self.next_token();
-        let expr = self.parse_expr(Precedence::Lowest)?;
+        self.depth -= 1;
+        let expr = self.parse_expr(Precedence::Lowest);
+        self.depth += 1;
+        let expr = expr?;
         if !self.expect_peek(Token::RPAREN) {
             return None;
         }
-        Some(expr)
+        Some(Expr {
+            span: start.to(self.cur_span),
+            ..expr
+        })
     }
 
     fn parse_if_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
         if !self.expect_peek(Token::LPAREN) {
             return None;
         }
@@ -245,10 +546,14 @@ impl<'a> Parser<'a> {
                 return None;
             }
         }
-        Some(Expr::If(Box::new(cond), cons, alt))
+        Some(Expr {
+            kind: ExprKind::If(Box::new(cond), cons, alt),
+            span: start.to(self.cur_span),
+        })
     }
 
     fn parse_function_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
         if !self.expect_peek(Token::LPAREN) {
             return None;
         }
@@ -256,8 +561,13 @@ impl<'a> Parser<'a> {
         if !self.expect_peek(Token::LBRACE) {
             return None;
         }
+        let loop_depth = std::mem::take(&mut self.loop_depth);
         let body = self.parse_block_stmt();
-        Some(Expr::Function(params, body))
+        self.loop_depth = loop_depth;
+        Some(Expr {
+            kind: ExprKind::Function(params, body),
+            span: start.to(self.cur_span),
+        })
     }
 
     fn parse_function_params(&mut self) -> Option<Vec<Ident>> {
@@ -286,27 +596,76 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_call_expr(&mut self, func: Expr) -> Option<Expr> {
-        let args = self.parse_call_args()?;
-        Some(Expr::Call(Box::new(func), args))
+        let args = self.parse_expr_list(Token::RPAREN)?;
+        Some(Expr {
+            span: func.span.to(self.cur_span),
+            kind: ExprKind::Call(Box::new(func), args),
+        })
     }
 
-    fn parse_call_args(&mut self) -> Option<Vec<Expr>> {
-        let mut args = Vec::new();
-        if self.is_peek_token(&Token::RPAREN) {
+    fn parse_array_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
+        let elements = self.parse_expr_list(Token::RBRACKET)?;
+        Some(Expr {
+            kind: ExprKind::Array(elements),
+            span: start.to(self.cur_span),
+        })
+    }
+
+    fn parse_index_expr(&mut self, left: Expr) -> Option<Expr> {
+        self.next_token();
+        let index = self.parse_expr(Precedence::Lowest)?;
+        if !self.expect_peek(Token::RBRACKET) {
+            return None;
+        }
+        Some(Expr {
+            span: left.span.to(self.cur_span),
+            kind: ExprKind::Index(Box::new(left), Box::new(index)),
+        })
+    }
+
+    fn parse_hash_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
+        let mut pairs = Vec::new();
+        while !self.is_peek_token(&Token::RBRACE) {
+            self.next_token();
+            let key = self.parse_expr(Precedence::Lowest)?;
+            if !self.expect_peek(Token::COLON) {
+                return None;
+            }
             self.next_token();
-            return Some(args);
+            let value = self.parse_expr(Precedence::Lowest)?;
+            pairs.push((key, value));
+            if !self.is_peek_token(&Token::RBRACE) && !self.expect_peek(Token::COMMA) {
+                return None;
+            }
+        }
+        if !self.expect_peek(Token::RBRACE) {
+            return None;
+        }
+        Some(Expr {
+            kind: ExprKind::Hash(pairs),
+            span: start.to(self.cur_span),
+        })
+    }
+
+    fn parse_expr_list(&mut self, end: Token) -> Option<Vec<Expr>> {
+        let mut exprs = Vec::new();
+        if self.is_peek_token(&end) {
+            self.next_token();
+            return Some(exprs);
         }
         self.next_token();
-        args.push(self.parse_expr(Precedence::Lowest)?);
+        exprs.push(self.parse_expr(Precedence::Lowest)?);
         while self.is_peek_token(&Token::COMMA) {
             self.next_token();
             self.next_token();
-            args.push(self.parse_expr(Precedence::Lowest)?);
+            exprs.push(self.parse_expr(Precedence::Lowest)?);
         }
-        if !self.expect_peek(Token::RPAREN) {
+        if !self.expect_peek(end) {
             return None;
         }
-        Some(args)
+        Some(exprs)
     }
 
     fn parse_ident(&mut self) -> Option<Ident> {
@@ -317,27 +676,126 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_ident_expr(&mut self) -> Option<Expr> {
-        self.parse_ident().map(Expr::Ident)
+        self.parse_ident().map(|ident| Expr {
+            kind: ExprKind::Ident(ident),
+            span: self.cur_span,
+        })
     }
 
     fn parse_int_expr(&mut self) -> Option<Expr> {
         match self.cur_token {
-            Token::INT(value) => Some(Expr::Int(value)),
+            Token::INT(value) => Some(Expr {
+                kind: ExprKind::Int(value),
+                span: self.cur_span,
+            }),
+            Token::BIGINT(ref value) => Some(Expr {
+                kind: ExprKind::BigInt(value.clone()),
+                span: self.cur_span,
+            }),
+            _ => None,
+        }
+    }
+
+    fn parse_float_expr(&mut self) -> Option<Expr> {
+        match self.cur_token {
+            Token::FLOAT(value) => Some(Expr {
+                kind: ExprKind::Float(value),
+                span: self.cur_span,
+            }),
             _ => None,
         }
     }
 
     fn parse_bool_expr(&mut self) -> Option<Expr> {
         match self.cur_token {
-            Token::BOOL(value) => Some(Expr::Bool(value)),
+            Token::BOOL(value) => Some(Expr {
+                kind: ExprKind::Bool(value),
+                span: self.cur_span,
+            }),
             _ => None,
         }
     }
 
     fn parse_string_expr(&mut self) -> Option<Expr> {
         match self.cur_token {
-            Token::STRING(ref value) => Some(Expr::String(value.clone())),
+            Token::STRING(ref value) => Some(Expr {
+                kind: ExprKind::String(value.clone()),
+                span: self.cur_span,
+            }),
             _ => None,
         }
     }
+
+    fn parse_template_expr(&mut self) -> Option<Expr> {
+        let start = self.cur_span;
+        let depth = self.template_depth;
+        let mut parts = Vec::new();
+        loop {
+            self.next_token();
+            match self.cur_token {
+                Token::TEMPLATEEND => break,
+                Token::TEMPLATE(ref value) => parts.push(TemplatePart::Literal(value.clone())),
+                Token::INTERPOLATION => {
+                    self.next_token();
+                    match self.parse_expr(Precedence::Lowest) {
+                        Some(expr) if self.expect_peek(Token::RBRACE) => {
+                            parts.push(TemplatePart::Expr(expr))
+                        }
+                        _ => {
+                            self.skip_template(depth)?;
+                            break;
+                        }
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(Expr {
+            kind: ExprKind::Template(parts),
+            span: start.to(self.cur_span),
+        })
+    }
+
+    fn skip_template(&mut self, depth: usize) -> Option<()> {
+        loop {
+            match self.cur_token {
+                Token::EOF => return None,
+                Token::TEMPLATEEND if self.template_depth == depth - 1 => return Some(()),
+                _ => self.next_token(),
+            }
+        }
+    }
+}
+
+fn find_jump(expr: &Expr) -> Option<&Stmt> {
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || match &expr.kind {
+        ExprKind::If(cond, cons, alt) => find_jump(cond)
+            .or_else(|| find_jump_in_block(cons))
+            .or_else(|| alt.as_ref().and_then(find_jump_in_block)),
+        ExprKind::Prefix(_, right) => find_jump(right),
+        ExprKind::Infix(_, left, right) | ExprKind::Index(left, right) => {
+            find_jump(left).or_else(|| find_jump(right))
+        }
+        ExprKind::Call(func, args) => find_jump(func).or_else(|| args.iter().find_map(find_jump)),
+        ExprKind::Array(elements) => elements.iter().find_map(find_jump),
+        ExprKind::Hash(pairs) => pairs
+            .iter()
+            .find_map(|(key, value)| find_jump(key).or_else(|| find_jump(value))),
+        ExprKind::Template(parts) => parts.iter().find_map(|part| match part {
+            TemplatePart::Expr(expr) => find_jump(expr),
+            TemplatePart::Literal(_) => None,
+        }),
+        _ => None,
+    })
+}
+
+fn find_jump_in_block(stmts: &BlockStmt) -> Option<&Stmt> {
+    stmts.iter().find_map(|stmt| match &stmt.kind {
+        StmtKind::Break | StmtKind::Continue => Some(stmt),
+        StmtKind::Expr(Expr {
+            kind: ExprKind::If(_, cons, alt),
+            ..
+        }) => find_jump_in_block(cons).or_else(|| alt.as_ref().and_then(find_jump_in_block)),
+        _ => None,
+    })
 }
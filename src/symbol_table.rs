@@ -0,0 +1,162 @@
+use crate::{builtins::BUILTINS, token::Span};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolScope {
+    Global,
+    Local,
+    Builtin,
+    Free,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    pub index: usize,
+    pub constant: Option<Span>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    outer: Option<Box<SymbolTable>>,
+    store: HashMap<String, Symbol>,
+    num_definitions: usize,
+    free_symbols: Vec<Symbol>,
+    pending: HashSet<String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        let mut symbol_table = SymbolTable::default();
+        for (index, (name, _)) in BUILTINS.iter().enumerate() {
+            symbol_table.define_builtin(index, name);
+        }
+        symbol_table
+    }
+
+    pub fn new_enclosed(outer: SymbolTable) -> Self {
+        SymbolTable {
+            outer: Some(Box::new(outer)),
+            ..Default::default()
+        }
+    }
+
+    pub fn into_outer(self) -> Option<SymbolTable> {
+        self.outer.map(|outer| *outer)
+    }
+
+    pub fn set_pending(&mut self, names: HashSet<String>) {
+        self.pending = names;
+    }
+
+    pub fn num_definitions(&self) -> usize {
+        self.num_definitions
+    }
+
+    pub fn free_symbols(&self) -> &[Symbol] {
+        &self.free_symbols
+    }
+
+    pub fn constant(&self, name: &str) -> Option<Span> {
+        self.store.get(name).and_then(|symbol| symbol.constant)
+    }
+
+    pub fn define(&mut self, name: &str) -> Symbol {
+        self.define_binding(name, None)
+    }
+
+    pub fn define_const(&mut self, name: &str, span: Span) -> Symbol {
+        self.define_binding(name, Some(span))
+    }
+
+    fn define_binding(&mut self, name: &str, constant: Option<Span>) -> Symbol {
+        let scope = if self.outer.is_some() {
+            SymbolScope::Local
+        } else {
+            SymbolScope::Global
+        };
+        let index = match self.store.get(name) {
+            Some(symbol) if symbol.scope == scope => symbol.index,
+            _ => {
+                self.num_definitions += 1;
+                self.num_definitions - 1
+            }
+        };
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope,
+            index,
+            constant,
+        };
+        self.store.insert(name.to_string(), symbol.clone());
+        symbol
+    }
+
+    pub fn define_forward(&mut self, name: &str) -> Symbol {
+        match self.outer {
+            Some(ref mut outer) => outer.define_forward(name),
+            None => self.define(name),
+        }
+    }
+
+    pub fn global_symbols(&self) -> Vec<Symbol> {
+        if let Some(ref outer) = self.outer {
+            return outer.global_symbols();
+        }
+        let mut symbols: Vec<Symbol> = self
+            .store
+            .values()
+            .filter(|symbol| symbol.scope == SymbolScope::Global)
+            .cloned()
+            .collect();
+        symbols.sort_by_key(|symbol| symbol.index);
+        symbols
+    }
+
+    fn define_builtin(&mut self, index: usize, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope: SymbolScope::Builtin,
+            index,
+            constant: None,
+        };
+        self.store.insert(name.to_string(), symbol.clone());
+        symbol
+    }
+
+    fn define_free(&mut self, original: Symbol) -> Symbol {
+        let symbol = Symbol {
+            name: original.name.clone(),
+            scope: SymbolScope::Free,
+            index: self.free_symbols.len(),
+            constant: original.constant,
+        };
+        self.free_symbols.push(original);
+        self.store.insert(symbol.name.clone(), symbol.clone());
+        symbol
+    }
+
+    pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
+        self.resolve_at(name, false)
+    }
+
+    fn resolve_at(&mut self, name: &str, enclosing: bool) -> Option<Symbol> {
+        if let Some(symbol) = self.store.get(name) {
+            return Some(symbol.clone());
+        }
+        if enclosing && self.pending.contains(name) {
+            return Some(self.define(name));
+        }
+        match self.outer {
+            Some(ref mut outer) => match outer.resolve_at(name, true)? {
+                symbol @ Symbol {
+                    scope: SymbolScope::Global | SymbolScope::Builtin,
+                    ..
+                } => Some(symbol),
+                symbol => Some(self.define_free(symbol)),
+            },
+            None => None,
+        }
+    }
+}
@@ -1,34 +1,85 @@
+use num_bigint::BigInt;
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     ILLEGAL,
     EOF,
     IDENT(String),
     INT(i64),
+    BIGINT(BigInt),
+    FLOAT(f64),
     BOOL(bool),
     STRING(String),
+    TEMPLATESTART,
+    TEMPLATE(String),
+    INTERPOLATION,
+    TEMPLATEEND,
+    COMMENT(String),
+    DOC(String),
     IF,
     ELSE,
     ASSIGN,
+    PLUSASSIGN,
+    MINUSASSIGN,
+    ASTERISKASSIGN,
+    SLASHASSIGN,
     PLUS,
     MINUS,
     BANG,
     ASTERISK,
     SLASH,
+    PERCENT,
+    POWER,
     LT,
     GT,
+    LE,
+    GE,
     EQ,
     NE,
+    AND,
+    OR,
     COMMA,
     SEMICOLON,
+    COLON,
     LPAREN,
     RPAREN,
     LBRACE,
     RBRACE,
+    LBRACKET,
+    RBRACKET,
     FUNCTION,
     LET,
+    CONST,
     RETURN,
+    WHILE,
+    FOR,
+    IN,
+    BREAK,
+    CONTINUE,
 }
 
 impl Display for Token {
@@ -38,29 +89,55 @@ impl Display for Token {
             Token::EOF => write!(f, "EOF"),
             Token::IDENT(value) => write!(f, "{}", value),
             Token::INT(value) => write!(f, "{}", value),
+            Token::BIGINT(value) => write!(f, "{}", value),
+            Token::FLOAT(value) => write!(f, "{:?}", value),
             Token::BOOL(value) => write!(f, "{}", value),
             Token::STRING(value) => write!(f, "{}", value),
+            Token::TEMPLATESTART | Token::TEMPLATEEND => write!(f, "`"),
+            Token::TEMPLATE(value) => write!(f, "{}", value),
+            Token::INTERPOLATION => write!(f, "${{"),
+            Token::COMMENT(value) => write!(f, "{}", value),
+            Token::DOC(value) => write!(f, "///{}", value),
             Token::IF => write!(f, "if"),
             Token::ELSE => write!(f, "else"),
             Token::ASSIGN => write!(f, "="),
+            Token::PLUSASSIGN => write!(f, "+="),
+            Token::MINUSASSIGN => write!(f, "-="),
+            Token::ASTERISKASSIGN => write!(f, "*="),
+            Token::SLASHASSIGN => write!(f, "/="),
             Token::PLUS => write!(f, "+"),
             Token::MINUS => write!(f, "-"),
             Token::BANG => write!(f, "!"),
             Token::ASTERISK => write!(f, "*"),
             Token::SLASH => write!(f, "/"),
+            Token::PERCENT => write!(f, "%"),
+            Token::POWER => write!(f, "**"),
             Token::LT => write!(f, "<"),
             Token::GT => write!(f, ">"),
+            Token::LE => write!(f, "<="),
+            Token::GE => write!(f, ">="),
             Token::EQ => write!(f, "=="),
             Token::NE => write!(f, "!="),
+            Token::AND => write!(f, "&&"),
+            Token::OR => write!(f, "||"),
             Token::COMMA => write!(f, ","),
             Token::SEMICOLON => write!(f, ";"),
+            Token::COLON => write!(f, ":"),
             Token::LPAREN => write!(f, "("),
             Token::RPAREN => write!(f, ")"),
             Token::LBRACE => write!(f, "{{"),
             Token::RBRACE => write!(f, "}}"),
+            Token::LBRACKET => write!(f, "["),
+            Token::RBRACKET => write!(f, "]"),
             Token::FUNCTION => write!(f, "fn"),
             Token::LET => write!(f, "let"),
+            Token::CONST => write!(f, "const"),
             Token::RETURN => write!(f, "return"),
+            Token::WHILE => write!(f, "while"),
+            Token::FOR => write!(f, "for"),
+            Token::IN => write!(f, "in"),
+            Token::BREAK => write!(f, "break"),
+            Token::CONTINUE => write!(f, "continue"),
         }
     }
 }
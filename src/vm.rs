@@ -0,0 +1,456 @@
+use crate::{
+    ast::{Infix, Prefix},
+    builtins::BUILTINS,
+    code::{read_u16, read_u8, Opcode},
+    compiler::Bytecode,
+    error::RuntimeError,
+    evaluator::{eval_index_expr, eval_infix_expr, eval_prefix_expr, is_truthy, iterate},
+    object::{Builtin, Closure, CompiledFunction, HashKey, Object},
+    symbol_table::Symbol,
+    token::Span,
+};
+use std::{cell::RefCell, collections::BTreeMap, mem, rc::Rc};
+
+const MAX_FRAMES: usize = 1024;
+const STACK_SIZE: usize = MAX_FRAMES * 512;
+
+#[derive(Debug, Clone)]
+enum Slot {
+    Value(Object),
+    Cell(Rc<RefCell<Object>>),
+}
+
+impl Slot {
+    fn get(&self) -> Object {
+        match self {
+            Slot::Value(object) => object.clone(),
+            Slot::Cell(cell) => cell.borrow().clone(),
+        }
+    }
+
+    fn into_object(self) -> Object {
+        match self {
+            Slot::Value(object) => object,
+            Slot::Cell(cell) => cell.borrow().clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base_pointer: usize,
+}
+
+#[derive(Debug)]
+pub struct Vm {
+    constants: Vec<Object>,
+    stack: Vec<Slot>,
+    globals: Vec<Option<Object>>,
+    global_symbols: Vec<Symbol>,
+    frames: Vec<Frame>,
+    last_popped: Option<Object>,
+}
+
+impl Vm {
+    pub fn new(bytecode: Bytecode) -> Self {
+        Vm::new_with_globals(bytecode, Vec::new())
+    }
+
+    pub fn new_with_globals(bytecode: Bytecode, globals: Vec<Option<Object>>) -> Self {
+        let main = CompiledFunction {
+            instructions: bytecode.instructions,
+            num_locals: 0,
+            num_params: 0,
+            doc: None,
+            span: Span::default(),
+            spans: bytecode.spans,
+            errors: bytecode.errors,
+        };
+        Vm {
+            constants: bytecode.constants,
+            stack: Vec::new(),
+            globals,
+            global_symbols: bytecode.global_symbols,
+            frames: vec![Frame {
+                closure: Rc::new(Closure {
+                    func: Rc::new(main),
+                    free: Vec::new(),
+                }),
+                ip: 0,
+                base_pointer: 0,
+            }],
+            last_popped: None,
+        }
+    }
+
+    pub fn into_globals(self) -> Vec<Option<Object>> {
+        self.globals
+    }
+
+    pub fn last_popped(&self) -> Option<Object> {
+        self.last_popped.clone()
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        self.execute().map_err(|err| {
+            if err.span() != Span::default() {
+                return err;
+            }
+            let frame = self.frames.last().unwrap();
+            err.with_span(frame.closure.func.span_at(frame.ip - 1))
+        })
+    }
+
+    fn execute(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let ins = &frame.closure.func.instructions;
+            if frame.ip >= ins.len() {
+                return Ok(());
+            }
+            let op = Opcode::try_from(ins[frame.ip]).unwrap();
+            frame.ip += 1;
+            match op {
+                Opcode::Constant => {
+                    let index = self.read_u16_operand();
+                    self.push(self.constants[index].clone())?;
+                }
+                Opcode::Pop => {
+                    self.last_popped = Some(self.pop());
+                }
+                Opcode::Drop => {
+                    let count = self.read_u8_operand();
+                    self.stack.truncate(self.stack.len() - count);
+                }
+                Opcode::Add
+                | Opcode::Sub
+                | Opcode::Mul
+                | Opcode::Div
+                | Opcode::Equal
+                | Opcode::NotEqual
+                | Opcode::GreaterThan
+                | Opcode::LessThan
+                | Opcode::Mod
+                | Opcode::Pow
+                | Opcode::LessEqual
+                | Opcode::GreaterEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let infix = match op {
+                        Opcode::Add => Infix::Plus,
+                        Opcode::Sub => Infix::Minus,
+                        Opcode::Mul => Infix::Asterisk,
+                        Opcode::Div => Infix::Slash,
+                        Opcode::Equal => Infix::Eq,
+                        Opcode::NotEqual => Infix::Ne,
+                        Opcode::GreaterThan => Infix::Gt,
+                        Opcode::LessThan => Infix::Lt,
+                        Opcode::Mod => Infix::Percent,
+                        Opcode::Pow => Infix::Power,
+                        Opcode::LessEqual => Infix::Le,
+                        Opcode::GreaterEqual => Infix::Ge,
+                        _ => unreachable!(),
+                    };
+                    self.push(eval_infix_expr(infix, left, right)?)?;
+                }
+                Opcode::True => self.push(Object::Bool(true))?,
+                Opcode::False => self.push(Object::Bool(false))?,
+                Opcode::Null => self.push(Object::Null)?,
+                Opcode::Minus => {
+                    let right = self.pop();
+                    self.push(eval_prefix_expr(Prefix::Minus, right)?)?;
+                }
+                Opcode::Bang => {
+                    let right = self.pop();
+                    self.push(eval_prefix_expr(Prefix::Bang, right)?)?;
+                }
+                Opcode::Jump => {
+                    let position = self.read_u16_operand();
+                    self.frames.last_mut().unwrap().ip = position;
+                }
+                Opcode::JumpNotTruthy => {
+                    let position = self.read_u16_operand();
+                    let cond = self.pop();
+                    if !is_truthy(cond) {
+                        self.frames.last_mut().unwrap().ip = position;
+                    }
+                }
+                Opcode::Iter => {
+                    let iterable = self.pop();
+                    self.push(Object::Array(iterate(iterable)?))?;
+                    self.push(Object::Int(0))?;
+                }
+                Opcode::ForIter => {
+                    let position = self.read_u16_operand();
+                    let len = self.stack.len();
+                    match (&self.stack[len - 2], &self.stack[len - 1]) {
+                        (Slot::Value(Object::Array(elements)), Slot::Value(Object::Int(index)))
+                            if (*index as usize) < elements.len() =>
+                        {
+                            let element = elements[*index as usize].clone();
+                            self.stack[len - 1] = Slot::Value(Object::Int(index + 1));
+                            self.push(element)?;
+                        }
+                        _ => {
+                            self.stack.truncate(len - 2);
+                            self.frames.last_mut().unwrap().ip = position;
+                        }
+                    }
+                }
+                Opcode::JumpNotTruthyOrPop | Opcode::JumpTruthyOrPop => {
+                    let position = self.read_u16_operand();
+                    let truthy =
+                        is_truthy(self.stack.last().map(Slot::get).unwrap_or(Object::Null));
+                    if truthy == (op == Opcode::JumpTruthyOrPop) {
+                        self.frames.last_mut().unwrap().ip = position;
+                    } else {
+                        self.pop();
+                    }
+                }
+                Opcode::SetGlobal => {
+                    let index = self.read_u16_operand();
+                    let object = self.pop();
+                    if index >= self.globals.len() {
+                        self.globals.resize(index + 1, None);
+                    }
+                    self.globals[index] = Some(object);
+                }
+                Opcode::GetGlobal => {
+                    let index = self.read_u16_operand();
+                    let object = self.globals.get(index).cloned().flatten().ok_or_else(|| {
+                        RuntimeError::UndefinedIdentifier {
+                            name: self.global_symbols[index].name.clone(),
+                            span: Span::default(),
+                        }
+                    })?;
+                    self.push(object)?;
+                }
+                Opcode::GetAssignGlobal => {
+                    let index = self.read_u16_operand();
+                    let object = self.assignable_global(index)?;
+                    self.push(object)?;
+                }
+                Opcode::AssignGlobal => {
+                    let index = self.read_u16_operand();
+                    self.assignable_global(index)?;
+                    if let Some(declared) = self.global_symbols[index].constant {
+                        return Err(RuntimeError::ConstantRebinding {
+                            name: self.global_symbols[index].name.clone(),
+                            span: Span::default(),
+                            declared,
+                        });
+                    }
+                    self.globals[index] = Some(self.pop());
+                }
+                Opcode::Raise => {
+                    let index = self.read_u16_operand();
+                    return Err(self.frames.last().unwrap().closure.func.errors[index].clone());
+                }
+                Opcode::SetLocal => {
+                    let index = self.read_u8_operand();
+                    let base_pointer = self.frames.last().unwrap().base_pointer;
+                    let object = self.pop();
+                    match &self.stack[base_pointer + index] {
+                        Slot::Cell(cell) => *cell.borrow_mut() = object,
+                        _ => self.stack[base_pointer + index] = Slot::Value(object),
+                    }
+                }
+                Opcode::GetLocal => {
+                    let index = self.read_u8_operand();
+                    let base_pointer = self.frames.last().unwrap().base_pointer;
+                    self.push(self.stack[base_pointer + index].get())?;
+                }
+                Opcode::GetBuiltin => {
+                    let index = self.read_u8_operand();
+                    let (name, func) = BUILTINS[index];
+                    self.push(Object::Builtin(Builtin { name, func }))?;
+                }
+                Opcode::Array => {
+                    let len = self.read_u16_operand();
+                    let elements = self.pop_objects(len);
+                    self.push(Object::Array(elements))?;
+                }
+                Opcode::Hash => {
+                    let len = self.read_u16_operand();
+                    let objects = self.pop_objects(len * 2);
+                    let mut pairs = BTreeMap::new();
+                    let mut objects = objects.into_iter();
+                    while let (Some(key), Some(value)) = (objects.next(), objects.next()) {
+                        pairs.insert(HashKey::try_from(&key)?, value);
+                    }
+                    self.push(Object::Hash(pairs))?;
+                }
+                Opcode::Template => {
+                    let len = self.read_u16_operand();
+                    let objects = self.pop_objects(len);
+                    let value = objects.iter().map(|object| object.to_string()).collect();
+                    self.push(Object::String(value))?;
+                }
+                Opcode::Index => {
+                    let index = self.pop();
+                    let left = self.pop();
+                    self.push(eval_index_expr(left, index)?)?;
+                }
+                Opcode::Call => {
+                    let num_args = self.read_u8_operand();
+                    self.call(num_args)?;
+                }
+                Opcode::Closure => {
+                    let index = self.read_u16_operand();
+                    let num_free = self.read_u8_operand();
+                    let func = match &self.constants[index] {
+                        Object::CompiledFunction(func) => Rc::clone(func),
+                        object => unreachable!("{} is not compiled function", object),
+                    };
+                    let free = self
+                        .stack
+                        .split_off(self.stack.len() - num_free)
+                        .into_iter()
+                        .map(|slot| match slot {
+                            Slot::Cell(cell) => cell,
+                            Slot::Value(object) => {
+                                unreachable!("{} is not captured variable", object)
+                            }
+                        })
+                        .collect();
+                    self.push(Object::Closure(Rc::new(Closure { func, free })))?;
+                }
+                Opcode::GetFree => {
+                    let index = self.read_u8_operand();
+                    let object = self.frames.last().unwrap().closure.free[index]
+                        .borrow()
+                        .clone();
+                    self.push(object)?;
+                }
+                Opcode::SetFree => {
+                    let index = self.read_u8_operand();
+                    let object = self.pop();
+                    *self.frames.last().unwrap().closure.free[index].borrow_mut() = object;
+                }
+                Opcode::CaptureLocal => {
+                    let index = self.read_u8_operand();
+                    let slot = self.frames.last().unwrap().base_pointer + index;
+                    if let Slot::Value(object) = &mut self.stack[slot] {
+                        let object = mem::replace(object, Object::Null);
+                        self.stack[slot] = Slot::Cell(Rc::new(RefCell::new(object)));
+                    }
+                    self.push_slot(self.stack[slot].clone())?;
+                }
+                Opcode::CaptureFree => {
+                    let index = self.read_u8_operand();
+                    let cell = Rc::clone(&self.frames.last().unwrap().closure.free[index]);
+                    self.push_slot(Slot::Cell(cell))?;
+                }
+                Opcode::ReturnValue => {
+                    let object = self.pop();
+                    if self.frames.len() == 1 {
+                        self.last_popped = Some(object);
+                        return Ok(());
+                    }
+                    let frame = self.frames.pop().unwrap();
+                    self.stack.truncate(frame.base_pointer - 1);
+                    self.push(object)?;
+                }
+                Opcode::Return => {
+                    let frame = self.frames.pop().unwrap();
+                    self.stack.truncate(frame.base_pointer - 1);
+                    self.push(Object::Null)?;
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, num_args: usize) -> Result<(), RuntimeError> {
+        let callee = self.stack[self.stack.len() - 1 - num_args].get();
+        match callee {
+            Object::Closure(closure) => {
+                let func = &closure.func;
+                if num_args != func.num_params {
+                    return Err(RuntimeError::ArityMismatch {
+                        expected: func.num_params,
+                        given: num_args,
+                        span: Span::default(),
+                        defined: Some(func.span),
+                    });
+                }
+                if self.frames.len() >= MAX_FRAMES {
+                    return Err(RuntimeError::RecursionLimit {
+                        limit: MAX_FRAMES,
+                        span: Span::default(),
+                    });
+                }
+                let base_pointer = self.stack.len() - num_args;
+                for _ in func.num_params..func.num_locals {
+                    self.push(Object::Null)?;
+                }
+                self.frames.push(Frame {
+                    closure,
+                    ip: 0,
+                    base_pointer,
+                });
+                Ok(())
+            }
+            Object::Builtin(builtin) => {
+                let args = self.pop_objects(num_args);
+                self.pop();
+                let object = (builtin.func)(args)?;
+                self.push(object)
+            }
+            object => Err(RuntimeError::NotCallable {
+                callee: object.to_string(),
+                span: Span::default(),
+            }),
+        }
+    }
+
+    fn read_u16_operand(&mut self) -> usize {
+        let frame = self.frames.last_mut().unwrap();
+        let operand = read_u16(&frame.closure.func.instructions, frame.ip);
+        frame.ip += 2;
+        operand
+    }
+
+    fn read_u8_operand(&mut self) -> usize {
+        let frame = self.frames.last_mut().unwrap();
+        let operand = read_u8(&frame.closure.func.instructions, frame.ip);
+        frame.ip += 1;
+        operand
+    }
+
+    fn assignable_global(&self, index: usize) -> Result<Object, RuntimeError> {
+        self.globals.get(index).cloned().flatten().ok_or_else(|| {
+            RuntimeError::UndeclaredAssignment {
+                name: self.global_symbols[index].name.clone(),
+                span: Span::default(),
+            }
+        })
+    }
+
+    fn push(&mut self, object: Object) -> Result<(), RuntimeError> {
+        self.push_slot(Slot::Value(object))
+    }
+
+    fn push_slot(&mut self, slot: Slot) -> Result<(), RuntimeError> {
+        if self.stack.len() >= STACK_SIZE {
+            return Err(RuntimeError::StackOverflow {
+                span: Span::default(),
+            });
+        }
+        self.stack.push(slot);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().unwrap().into_object()
+    }
+
+    fn pop_objects(&mut self, len: usize) -> Vec<Object> {
+        self.stack
+            .split_off(self.stack.len() - len)
+            .into_iter()
+            .map(Slot::into_object)
+            .collect()
+    }
+}
@@ -0,0 +1,250 @@
+use rust_monkey::{
+    ast::{Program, StmtKind},
+    compiler::Compiler,
+    environment::Environment,
+    error::RuntimeError,
+    evaluator::Evaluator,
+    lexer::Lexer,
+    object::Object,
+    parser::Parser,
+    token::Span,
+    vm::Vm,
+};
+use std::{cell::RefCell, rc::Rc};
+
+fn parse(input: &str) -> Result<Program, Vec<String>> {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program();
+    let errors = parser.errors();
+    if !errors.is_empty() {
+        return Err(errors.into_iter().filter_map(|err| err.code).collect());
+    }
+    Ok(program)
+}
+
+fn eval(program: Program) -> Result<Object, RuntimeError> {
+    let env = Rc::new(RefCell::new(Environment::new()));
+    Evaluator::new(env).eval(program)
+}
+
+fn run_vm(program: Program) -> Result<Object, RuntimeError> {
+    let mut compiler = Compiler::new();
+    compiler.compile(&program)?;
+    let mut vm = Vm::new(compiler.bytecode());
+    vm.run()?;
+    Ok(vm.last_popped().unwrap_or(Object::Null))
+}
+
+fn run(input: &str) -> [Result<String, (&'static str, Span)>; 2] {
+    let program = parse(input).unwrap();
+    [eval(program.clone()), run_vm(program)].map(|result| {
+        result
+            .map(|object| object.to_string())
+            .map_err(|err| (err.code(), err.span()))
+    })
+}
+
+fn assert_output(input: &str, expected: &str) {
+    let [eval, vm] = run(input);
+    assert_eq!(eval, Ok(expected.to_string()), "eval: {}", input);
+    assert_eq!(vm, Ok(expected.to_string()), "vm: {}", input);
+}
+
+fn assert_error(input: &str, code: &str, snippet: &str) {
+    let [eval, vm] = run(input);
+    let (eval_code, span) = eval.expect_err(input);
+    assert_eq!(eval_code, code, "eval: {}", input);
+    assert_eq!(&input[span.start..span.end], snippet, "eval: {}", input);
+    assert_eq!(vm, Err((eval_code, span)), "vm: {}", input);
+}
+
+#[test]
+fn test_mutual_recursion() {
+    assert_output(
+        "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; [even(10), odd(7), even(3)]",
+        "[true, true, false]",
+    );
+}
+
+#[test]
+fn test_program_result() {
+    assert_output("1; let a = 2;", "null");
+    assert_output("1; let a = 2; a += 1", "null");
+    assert_output("1; for (x in [1, 2]) { x }", "null");
+    assert_output("1; while (false) {} 3", "3");
+}
+
+#[test]
+fn test_forward_globals() {
+    assert_error("let early = fn() { later }; early()", "E1004", "later");
+    assert_error("let a = a;", "E1004", "a");
+}
+
+#[test]
+fn test_recursion_limit() {
+    assert_error("let f = fn(n) { f(n + 1) + 1 }; f(0)", "E1012", "f(n + 1)");
+    assert_error(
+        "let f = fn(a, b) { let x = [a, b]; f(a, b) + len(x) }; f(1, 2)",
+        "E1012",
+        "f(a, b)",
+    );
+}
+
+#[test]
+fn test_error_spans() {
+    assert_error("let f = fn(x) { x }; f(1, 2)", "E1005", "f(1, 2)");
+    assert_error("let f = fn(x) { -x }; f(true)", "E1002", "-x");
+}
+
+#[test]
+fn test_loop_control() {
+    assert_output(
+        "let xs = []; for (x in [1, 2, 3, 4]) { if (x == 2) { continue; } if (x == 4) { if (true) { break; } } let xs = push(xs, x); } xs",
+        "[1, 3]",
+    );
+    assert_output(
+        "let i = 0; while (true) { i += 1; if (i > 2) { break; } } i",
+        "3",
+    );
+    assert_output("for (x in [1, 2]) { break; }", "null");
+}
+
+#[test]
+fn test_loop_control_in_expressions() {
+    for input in [
+        "for (i in [1]) { let x = if (true) { break; }; }",
+        "for (i in [1]) { puts(if (true) { break; }) }",
+        "let n = 0; for (i in [1]) { n += if (true) { continue; } }",
+        "while (true) { [if (true) { if (true) { break; } }] }",
+    ] {
+        assert_eq!(parse(input).unwrap_err(), vec!["E0007"], "{}", input);
+    }
+}
+
+#[test]
+fn test_captured_assignment() {
+    assert_output(
+        "let counter = fn() { let c = 0; fn() { c += 1; c } }; let next = counter(); let other = counter(); [next(), next(), other(), next()]",
+        "[1, 2, 1, 3]",
+    );
+    assert_output(
+        "let f = fn() { let n = 1; let inc = fn() { fn() { n *= 10 } }(); inc(); inc(); n }; f()",
+        "100",
+    );
+    assert_output(
+        "let f = fn() { let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(10) }; f()",
+        "3628800",
+    );
+    assert_output("let f = fn() { f }; let g = f; let f = 9; g()", "9");
+}
+
+#[test]
+fn test_assignment_errors() {
+    assert_output("let f = fn() { y = 5 }; let y = 0; f(); y", "5");
+    assert_output("const c = 1; let f = fn() { c = 2 }; 7", "7");
+    assert_error("let f = fn() { c = 2 }; const c = 1; f()", "E1016", "c = 2");
+    assert_error("let f = fn() { y += 1 }; f()", "E1014", "y += 1");
+    assert_error("len = 1", "E1014", "len = 1");
+    assert_error("let f = fn() { const k = 1; k = 2 }; f()", "E1016", "k = 2");
+    assert_output("const c = 1; for (c in []) {} 3", "3");
+}
+
+#[test]
+fn test_forward_locals() {
+    assert_output(
+        "let outer = fn() { let g = fn() { h() }; let h = fn() { 1 }; g() }; outer()",
+        "1",
+    );
+    assert_output(
+        "let f = fn(n) { let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; [even(n), odd(n)] }; f(7)",
+        "[false, true]",
+    );
+}
+
+#[test]
+fn test_checked_arithmetic() {
+    assert_error("1 / 0", "E1010", "1 / 0");
+    assert_error("let x = 5; x % 0", "E1010", "x % 0");
+    assert_error("2 ** -1", "E1017", "2 ** -1");
+    assert_error("2 ** 4294967296", "E1018", "2 ** 4294967296");
+    assert_output("-(-9223372036854775807 - 1)", "9223372036854775808");
+    assert_output("(-9223372036854775807 - 1) / -1", "9223372036854775808");
+}
+
+#[test]
+fn test_big_integers() {
+    assert_output("99999999999999999999", "99999999999999999999");
+    assert_output("9223372036854775807 + 1", "9223372036854775808");
+    assert_output("2 ** 64 - 1", "18446744073709551615");
+    assert_output("-9223372036854775808", "-9223372036854775808");
+    assert_output(
+        "let one = 99999999999999999999 - 99999999999999999998; [one, {1: \"int\"}[one]]",
+        "[1, int]",
+    );
+}
+
+#[test]
+fn test_floats() {
+    assert_output(
+        "[1 + 0.5, 3 / 2.0, 2 * 1.5e3, 1.0 == 1, 2 > 1.5]",
+        "[1.5, 1.5, 3000.0, true, true]",
+    );
+    assert_error("1.0 / 0", "E1010", "1.0 / 0");
+    for literal in ["0.30000000000000004", "1e-9", "2.0", "1e300", "-0.5"] {
+        assert_output(literal, literal);
+    }
+    assert_output("0.1 + 0.2", "0.30000000000000004");
+}
+
+#[test]
+fn test_int_comparisons() {
+    assert_output(
+        "[1 < 2, 2 <= 2, 3 > 4, 5 >= 5, 1 == 1, 1 != 1, 9223372036854775807 + 1 > 0]",
+        "[true, true, false, true, true, false, true]",
+    );
+}
+
+#[test]
+fn test_templates() {
+    assert_output(
+        "let name = \"monkey\"; `hi ${name}, ${`nested ${1 + 2}`} \\${no}`",
+        "hi monkey, nested 3 ${no}",
+    );
+    let depth = 1000;
+    let input = format!("{}1{}", "`${".repeat(depth), "}`".repeat(depth));
+    assert_output(&input, "1");
+}
+
+#[test]
+fn test_nesting_limit() {
+    let chain = vec!["1"; 2000].join(" + ");
+    assert_output(&chain, "2000");
+    let calls = format!(
+        "let f = fn(x) {{ x }}; {}1{}",
+        "f(".repeat(1000),
+        ")".repeat(1000)
+    );
+    assert_output(&calls, "1");
+    let chain = vec!["1"; 5000].join(" + ");
+    assert_eq!(parse(&chain).unwrap_err(), vec!["E0003"]);
+}
+
+#[test]
+fn test_deep_template_nesting() {
+    let depth = 20000;
+    let input = format!("{}1{}", "`${".repeat(depth), "}`".repeat(depth));
+    assert_eq!(parse(&input).unwrap_err(), vec!["E0003"]);
+}
+
+#[test]
+fn test_doc_comments() {
+    let input = "/// Adds one.\n/// Really.\nlet inc = fn(x) { x + 1 }; doc(inc)";
+    match &parse(input).unwrap()[0].kind {
+        StmtKind::Let(_, _, doc) => assert_eq!(doc.as_deref(), Some("Adds one.\nReally.")),
+        kind => panic!("expected let, got {:?}", kind),
+    }
+    assert_output(input, "Adds one.\nReally.");
+    assert_output("let f = fn() { 1 }; doc(f)", "null");
+    assert_output("//// Not doc.\nlet f = fn() { 1 }; doc(f)", "null");
+    assert_error("doc(1)", "E1009", "doc(1)");
+}